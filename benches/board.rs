@@ -0,0 +1,158 @@
+//! Compares the bitboard `Board` with the `HashMap` grid it replaced.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use connect_four::{BitBoard, Board, ElementType};
+
+/// The grid as it was stored before the bitboard, kept here as a baseline
+mod hashmap {
+    use std::collections::HashMap;
+
+    use connect_four::ElementType;
+
+    pub struct Grid {
+        pub width: u32,
+        pub height: u32,
+        pub elements: HashMap<(u32, u32), ElementType>,
+    }
+
+    impl Grid {
+        pub fn new(width: u32, height: u32) -> Grid {
+            Grid {
+                width,
+                height,
+                elements: HashMap::new(),
+            }
+        }
+
+        pub fn add_at_column(&mut self, column: u32, element_type: ElementType) -> Option<u32> {
+            let row = (0..self.height).find(|&y| !self.elements.contains_key(&(column, y)))?;
+            self.elements.insert((column, row), element_type);
+            Some(row)
+        }
+
+        pub fn remove_top(&mut self, column: u32) {
+            if let Some(row) = (0..self.height)
+                .rev()
+                .find(|&y| self.elements.contains_key(&(column, y)))
+            {
+                self.elements.remove(&(column, row));
+            }
+        }
+
+        /// Scans every line of every direction, like `straight_matches` did
+        pub fn has_match(&self) -> bool {
+            for (dx, dy) in [(1i32, 0i32), (0, 1), (1, 1), (1, -1)] {
+                for x in 0..self.width as i32 {
+                    for y in 0..self.height as i32 {
+                        let Some(&first) = self.elements.get(&(x as u32, y as u32)) else {
+                            continue;
+                        };
+                        let line = (1..4).all(|i| {
+                            let (x, y) = (x + dx * i, y + dy * i);
+                            x >= 0
+                                && y >= 0
+                                && self.elements.get(&(x as u32, y as u32)) == Some(&first)
+                        });
+                        if line {
+                            return true;
+                        }
+                    }
+                }
+            }
+            false
+        }
+    }
+}
+
+/// A game that fills most of the board without a line for a long time
+const GAME: [u32; 30] = [
+    3, 3, 3, 3, 3, 3, 2, 4, 4, 2, 2, 4, 4, 2, 1, 5, 5, 1, 1, 5, 5, 1, 0, 6, 6, 0, 0, 6, 6, 0,
+];
+
+/// Counts the positions reachable in `depth` plies, checking for a win after every move
+fn perft_bits(board: &mut BitBoard, player: ElementType, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    for column in 0..board.width() {
+        if !board.can_play(column) {
+            continue;
+        }
+        board.play(column, player);
+        nodes += if board.is_win(player) {
+            1
+        } else {
+            perft_bits(board, player.other(), depth - 1)
+        };
+        board.undo(column);
+    }
+    nodes
+}
+
+fn perft_hashmap(grid: &mut hashmap::Grid, player: ElementType, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    for column in 0..grid.width {
+        if grid.add_at_column(column, player).is_none() {
+            continue;
+        }
+        nodes += if grid.has_match() {
+            1
+        } else {
+            perft_hashmap(grid, player.other(), depth - 1)
+        };
+        grid.remove_top(column);
+    }
+    nodes
+}
+
+fn play_game(c: &mut Criterion) {
+    let mut group = c.benchmark_group("play game");
+    group.bench_function("bitboard", |b| {
+        b.iter(|| {
+            let mut board = Board::default();
+            let mut player = ElementType::Red;
+            for column in GAME {
+                board.add_at_column(column, player).unwrap();
+                black_box(board.get_matches());
+                player = player.other();
+            }
+        })
+    });
+    group.bench_function("hashmap", |b| {
+        b.iter(|| {
+            let mut grid = hashmap::Grid::new(7, 6);
+            let mut player = ElementType::Red;
+            for column in GAME {
+                grid.add_at_column(column, player).unwrap();
+                black_box(grid.has_match());
+                player = player.other();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft 5");
+    group.bench_function("bitboard", |b| {
+        b.iter(|| perft_bits(&mut BitBoard::new(7, 6), ElementType::Red, black_box(5)))
+    });
+    group.bench_function("hashmap", |b| {
+        b.iter(|| {
+            perft_hashmap(
+                &mut hashmap::Grid::new(7, 6),
+                ElementType::Red,
+                black_box(5),
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, play_game, search);
+criterion_main!(benches);
@@ -0,0 +1,263 @@
+use std::time::{Duration, Instant};
+
+use crate::bitboard::{centre_out, BitBoard};
+use crate::board::ElementType;
+use crate::game::GameState;
+
+/// Score of a won position, a quicker win scores higher
+const WIN_SCORE: i32 = 1_000_000;
+
+/// How hard the computer thinks about its move
+#[derive(Clone, Debug)]
+pub struct AiConfig {
+    /// Number of plies to look ahead
+    pub depth: u32,
+    /// Stop deepening the search once this much time has passed
+    pub time_budget: Option<Duration>,
+}
+
+impl Default for AiConfig {
+    fn default() -> AiConfig {
+        AiConfig {
+            depth: 6,
+            time_budget: None,
+        }
+    }
+}
+
+/// Picks a column for the player to move with a negamax search and alpha-beta pruning.
+///
+/// The search is deepened one ply at a time up to `config.depth`, so when the time
+/// budget runs out the best move of the last finished depth is returned.
+/// Returns `None` when there is no legal move.
+pub fn best_move(game: &GameState, config: &AiConfig) -> Option<u32> {
+    if game.is_over() {
+        return None;
+    }
+    let mut search = Search {
+        deadline: config.time_budget.map(|budget| Instant::now() + budget),
+        bits: game.board().bits().clone(),
+    };
+    let mut best = centre_out(game.board().width()).find(|&column| game.board().can_play(column));
+    for depth in 1..=config.depth.max(1) {
+        match search.root(game.turn(), depth) {
+            Some(column) => best = Some(column),
+            None => break,
+        }
+    }
+    best
+}
+
+struct Search {
+    deadline: Option<Instant>,
+    bits: BitBoard,
+}
+
+impl Search {
+    fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Returns the best column at this depth or `None` if the search ran out of time
+    fn root(&mut self, player: ElementType, depth: u32) -> Option<u32> {
+        let mut best = None;
+        let mut alpha = -WIN_SCORE * 2;
+        let beta = WIN_SCORE * 2;
+        for column in centre_out(self.bits.width()) {
+            if !self.bits.can_play(column) {
+                continue;
+            }
+            let score = -self.child(column, player, depth, -beta, -alpha)?;
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some(column);
+            }
+        }
+        best
+    }
+
+    /// Plays the column, scores the position for the opponent and takes the move back
+    fn child(
+        &mut self,
+        column: u32,
+        player: ElementType,
+        depth: u32,
+        alpha: i32,
+        beta: i32,
+    ) -> Option<i32> {
+        self.bits.play(column, player);
+        let score = if self.bits.is_win(player) {
+            // The move won, so the opponent has lost
+            Some(-WIN_SCORE - depth as i32)
+        } else if self.bits.is_full() {
+            Some(0)
+        } else {
+            self.negamax(player.other(), depth - 1, alpha, beta)
+        };
+        self.bits.undo(column);
+        score
+    }
+
+    fn negamax(
+        &mut self,
+        player: ElementType,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> Option<i32> {
+        if self.timed_out() {
+            return None;
+        }
+        if depth == 0 {
+            return Some(evaluate(&self.bits, player));
+        }
+        let mut best = -WIN_SCORE * 2;
+        for column in centre_out(self.bits.width()) {
+            if !self.bits.can_play(column) {
+                continue;
+            }
+            let score = -self.child(column, player, depth, -beta, -alpha)?;
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Some(best)
+    }
+}
+
+/// Heuristic value of the board for the given player.
+///
+/// Every window of winning length that only one player occupies is a possible line:
+/// windows one or two pieces short of a line (open threes and twos in the usual game)
+/// count for that player, central pieces get a small bonus.
+pub fn evaluate(board: &BitBoard, player: ElementType) -> i32 {
+    let mut score = 0;
+    let centre = board.width() / 2;
+    for y in 0..board.height() {
+        match board.get(centre, y) {
+            Some(owner) if owner == player => score += 3,
+            Some(_) => score -= 3,
+            None => {}
+        }
+    }
+    for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+        for x in 0..board.width() {
+            for y in 0..board.height() {
+                score += score_window(board, player, (x, y), (dx, dy));
+            }
+        }
+    }
+    score
+}
+
+fn score_window(
+    board: &BitBoard,
+    player: ElementType,
+    (x, y): (u32, u32),
+    (dx, dy): (i32, i32),
+) -> i32 {
+    let length = board.win_length() as i32;
+    let mut own = 0;
+    let mut other = 0;
+    for i in 0..length {
+        let x = x as i32 + dx * i;
+        let y = y as i32 + dy * i;
+        if x < 0 || y < 0 || x >= board.width() as i32 || y >= board.height() as i32 {
+            return 0;
+        }
+        match board.get(x as u32, y as u32) {
+            Some(owner) if owner == player => own += 1,
+            Some(_) => other += 1,
+            None => {}
+        }
+    }
+    match (length - own, length - other) {
+        (1, _) if other == 0 => 5,
+        (2, _) if other == 0 => 2,
+        (_, 1) if own == 0 => -4,
+        (_, 2) if own == 0 => -2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::game::Outcome;
+
+    fn played(columns: &[u32]) -> GameState {
+        let mut game = GameState::default();
+        for &column in columns {
+            game.play(column).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn takes_an_immediate_win() {
+        // Red has three in the first column and blue none anywhere
+        let game = played(&[0, 1, 0, 1, 0, 6]);
+        for depth in 1..=4 {
+            let config = AiConfig {
+                depth,
+                ..AiConfig::default()
+            };
+            assert_eq!(best_move(&game, &config), Some(0));
+        }
+    }
+
+    #[test]
+    fn blocks_the_immediate_win_of_the_opponent() {
+        let game = played(&[0, 1, 0, 1, 0]);
+        assert_eq!(game.turn(), ElementType::Blue);
+        for depth in 2..=4 {
+            let config = AiConfig {
+                depth,
+                ..AiConfig::default()
+            };
+            assert_eq!(best_move(&game, &config), Some(0));
+        }
+    }
+
+    #[test]
+    fn there_is_no_move_in_a_finished_game() {
+        let won = played(&[0, 1, 0, 1, 0, 1, 0]);
+        assert_eq!(won.outcome(), Some(Outcome::Win(ElementType::Red)));
+        assert_eq!(best_move(&won, &AiConfig::default()), None);
+
+        let mut full = GameState::new(Board::with_win_length(3, 2, 3));
+        for column in [0, 1, 2, 0, 1, 2] {
+            full.play(column).unwrap();
+        }
+        assert_eq!(full.outcome(), Some(Outcome::Draw));
+        assert_eq!(best_move(&full, &AiConfig::default()), None);
+    }
+
+    #[test]
+    fn never_picks_a_full_column() {
+        // The centre column is full, the search would try it first
+        let game = played(&[3, 3, 3, 3, 3, 3]);
+        for depth in 1..=4 {
+            let config = AiConfig {
+                depth,
+                ..AiConfig::default()
+            };
+            let column = best_move(&game, &config).unwrap();
+            assert!(game.board().can_play(column), "column {}", column);
+        }
+    }
+
+    #[test]
+    fn a_spent_time_budget_still_gives_a_legal_move() {
+        let config = AiConfig {
+            depth: 20,
+            time_budget: Some(Duration::ZERO),
+        };
+        let column = best_move(&played(&[3, 3, 3, 3, 3, 3]), &config).unwrap();
+        assert_ne!(column, 3);
+    }
+}
@@ -0,0 +1,1036 @@
+use std::collections::HashSet;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use bevy::{
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+    window::PrimaryWindow,
+};
+
+use connect_four::{
+    ai::{self, AiConfig},
+    i18n::Catalog,
+    notation::GameRecord,
+    solver::{Solution, Solver},
+    Board, ElemError, ElementType, GameState, Mask, MoveHistory, Outcome, Pos, Settings,
+};
+
+use crate::cli::{Options, Start};
+use animation::{fall, pieces_settled, Falling, PieceLanded};
+use controls::ControlsPlugin;
+use highlight::HighlightPlugin;
+use hud::HudPlugin;
+use layout::LayoutPlugin;
+use net::{Link, NetPlugin, Role};
+use preview::PreviewPlugin;
+use replay::{Replay, ReplayPlugin};
+
+mod animation;
+mod controls;
+mod highlight;
+mod hud;
+mod layout;
+mod net;
+mod preview;
+mod replay;
+
+/// The game shown on the screen
+#[derive(Component, Debug, Default, Deref, DerefMut)]
+struct Grid(GameState);
+
+/// One piece on the board, lives as long as its cell is occupied
+#[derive(Component)]
+struct Piece {
+    pos: UVec2,
+    owner: ElementType,
+}
+
+/// Textures of the red and blue pieces, loaded once at startup
+#[derive(Resource)]
+struct PieceSprites([Handle<Image>; 2]);
+
+impl PieceSprites {
+    fn get(&self, owner: ElementType) -> Handle<Image> {
+        self.0[owner.index() as usize].clone()
+    }
+}
+
+/// Sent whenever pieces were added to or removed from the `Grid`
+#[derive(Event)]
+struct BoardChanged;
+
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+enum AppState {
+    #[default]
+    Menu,
+    Playing,
+    /// The winner or the draw is kept in the `Grid`
+    GameOver,
+    /// Stepping through a recorded game
+    Replay,
+    /// Waiting for the other player of a network game
+    Connecting,
+}
+
+/// Text shown over the board in the menu and after the game
+#[derive(Component)]
+struct Overlay;
+
+/// Board size and rules of the game on the screen
+#[derive(Resource, Deref)]
+struct GameSettings(Settings);
+
+/// Messages in the language of the user, every text on the screen comes from here
+#[derive(Resource, Deref)]
+struct Locale(Catalog);
+
+#[derive(Resource)]
+struct CursorWorldPos(Option<Vec2>);
+
+/// The finger that moves the selection, other touches are ignored until it is lifted
+#[derive(Resource, Default)]
+struct ActiveTouch(Option<u64>);
+
+#[derive(Resource)]
+struct Column(Option<u32>);
+
+/// A request to drop a piece of the current player into the column
+#[derive(Event)]
+struct DropPiece {
+    column: u32,
+}
+
+/// A game given on the command line, applied once the board is spawned
+#[derive(Resource)]
+struct StartRecord(GameRecord);
+
+/// Moves of the current game, for undo and redo
+#[derive(Resource, Default, Deref, DerefMut)]
+struct History(MoveHistory);
+
+#[derive(Event, Clone, Copy)]
+enum HistoryCommand {
+    Undo,
+    Redo,
+}
+
+#[derive(Component, Clone, Copy)]
+struct HistoryButton(HistoryCommand);
+
+/// A request to clear the board and start again
+#[derive(Event)]
+struct NewGame;
+
+/// A message for the user, the HUD shows it for a few seconds
+#[derive(Event)]
+struct Notice(String);
+
+/// The side played by someone who is not at this screen, `None` when two
+/// people play here
+#[derive(Resource)]
+struct Opponent {
+    side: Option<ElementType>,
+    kind: OpponentKind,
+}
+
+enum OpponentKind {
+    Computer(AiConfig),
+    /// The player at the other end of a network game
+    Network,
+    /// Both players are on other machines, this screen only watches
+    Watching,
+}
+
+impl Opponent {
+    /// Whether the side is played by someone at this screen
+    fn is_local(&self, side: ElementType) -> bool {
+        self.side != Some(side) && !matches!(self.kind, OpponentKind::Watching)
+    }
+}
+
+/// Number of people watching the network game
+#[derive(Resource, Default)]
+struct Spectators(u32);
+
+/// Move search running in the background for the computer
+#[derive(Resource)]
+struct AiTask(Task<Option<u32>>);
+
+/// Perfect play results for every column of the position with the given key
+#[derive(Resource, Default)]
+struct Hints {
+    enabled: bool,
+    key: Option<Mask>,
+    columns: Vec<Option<Solution>>,
+    /// Kept between searches, so that the large table is allocated only once
+    solver: Arc<Mutex<Option<Solver>>>,
+}
+
+/// Solver running in the background for the position with the given key
+#[derive(Resource)]
+struct HintTask {
+    key: Mask,
+    stop: Arc<AtomicBool>,
+    task: Task<Vec<Option<Solution>>>,
+}
+
+/// Text above the hovered column with the hint for that move
+#[derive(Component)]
+struct HintText;
+
+pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+
+/// Side of a cell in world units, the camera scales it to the window
+const ELEMENT_SIZE: f32 = 80.;
+
+/// Where Ctrl+S saves the game and Ctrl+O loads it from
+const SAVE_PATH: &str = "saved_game.c4";
+
+/// Opens the game window. When the computer side or a saved game is given
+/// on the command line the menu is skipped and the game starts right away.
+pub fn run(settings: Settings, start: Start, options: &Options, catalog: Catalog) {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
+        .insert_resource(GameSettings(settings))
+        .insert_resource(Locale(catalog))
+        .insert_resource(CursorWorldPos(None))
+        .init_resource::<ActiveTouch>()
+        .insert_resource(Column(None))
+        .insert_resource(Opponent {
+            side: options.ai,
+            kind: match start {
+                // Сторону соперника по сети узнаём при подключении
+                Start::Host(_) | Start::Join(_) => OpponentKind::Network,
+                Start::Watch(_) => OpponentKind::Watching,
+                _ => OpponentKind::Computer(options.ai_config()),
+            },
+        })
+        .init_resource::<Hints>()
+        .init_resource::<History>()
+        .init_resource::<Spectators>()
+        .add_event::<DropPiece>()
+        .add_event::<PieceLanded>()
+        .add_event::<BoardChanged>()
+        .add_event::<HistoryCommand>()
+        .add_event::<NewGame>()
+        .add_event::<Notice>();
+    app.add_plugins((
+        ControlsPlugin,
+        HighlightPlugin,
+        HudPlugin,
+        LayoutPlugin,
+        PreviewPlugin,
+        ReplayPlugin {
+            speed: options.replay_speed.unwrap_or(1.),
+        },
+    ));
+    match start {
+        Start::NewGame if options.ai.is_none() => {
+            app.init_state::<AppState>();
+        }
+        Start::NewGame => {
+            app.insert_state(AppState::Playing);
+        }
+        Start::Continue(record) => {
+            app.insert_state(AppState::Playing)
+                .insert_resource(StartRecord(record));
+        }
+        Start::Replay(record) => {
+            app.insert_state(AppState::Replay)
+                .insert_resource(Replay::new(record));
+        }
+        Start::Host(addr) => {
+            app.insert_state(AppState::Connecting).add_plugins(NetPlugin {
+                role: Role::Host(addr),
+            });
+        }
+        Start::Join(addr) => {
+            app.insert_state(AppState::Connecting).add_plugins(NetPlugin {
+                role: Role::Join(addr),
+            });
+        }
+        Start::Watch(addr) => {
+            app.insert_state(AppState::Connecting).add_plugins(NetPlugin {
+                role: Role::Watch(addr),
+            });
+        }
+    }
+    app.add_systems(
+        Startup,
+        (
+            install_font,
+            setup,
+            apply_start_record.run_if(resource_exists::<StartRecord>),
+        )
+            .chain(),
+    )
+        .add_systems(OnEnter(AppState::Menu), show_menu)
+        .add_systems(OnExit(AppState::Menu), despawn_overlay)
+        .add_systems(OnEnter(AppState::GameOver), show_game_over)
+        .add_systems(OnExit(AppState::GameOver), despawn_overlay)
+        .add_systems(
+            Update,
+            (
+                get_cursor_world_pos,
+                (
+                    check_mouse_pos.run_if(resource_changed::<CursorWorldPos>),
+                    click_column
+                        .run_if(input_just_pressed(MouseButton::Left).or_else(touch_released))
+                        .run_if(in_state(AppState::Playing))
+                        .run_if(pieces_settled),
+                    start_ai.run_if(in_state(AppState::Playing)),
+                    poll_ai
+                        .run_if(resource_exists::<AiTask>)
+                        .run_if(pieces_settled),
+                    spawn_element
+                        .run_if(in_state(AppState::Playing))
+                        .run_if(pieces_settled),
+                    fall,
+                    finish_move,
+                    history_keys,
+                    press_history_button,
+                    // По сети отмена разошлась бы с доской соперника
+                    apply_history
+                        .run_if(in_game)
+                        .run_if(pieces_settled)
+                        .run_if(not(resource_exists::<Link>)),
+                    choose_mode.run_if(in_state(AppState::Menu)),
+                    new_game
+                        .run_if(in_game)
+                        .run_if(input_just_pressed(KeyCode::KeyR).or_else(on_event::<NewGame>()))
+                        .run_if(not(resource_exists::<Link>)),
+                    open_replay
+                        .run_if(in_state(AppState::Menu).or_else(in_state(AppState::GameOver)))
+                        .run_if(input_just_pressed(KeyCode::KeyP))
+                        .run_if(not(resource_exists::<Link>)),
+                    end_touch,
+                    draw,
+                )
+                    .chain(),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                save_game.run_if(ctrl_just_pressed(KeyCode::KeyS)),
+                // В режиме просмотра загрузка испортила бы разбираемую запись
+                load_game
+                    .run_if(in_state(AppState::Menu).or_else(in_game))
+                    .run_if(ctrl_just_pressed(KeyCode::KeyO))
+                    .run_if(pieces_settled)
+                    .run_if(not(resource_exists::<Link>)),
+            )
+                .before(spawn_element),
+        )
+        .add_systems(
+            Update,
+            (
+                toggle_hints.run_if(input_just_pressed(KeyCode::KeyH)),
+                start_hints,
+                poll_hints.run_if(resource_exists::<HintTask>),
+                show_hint,
+            )
+                .chain()
+                .after(check_mouse_pos),
+        )
+        .run();
+}
+
+/// Whether a game is being played or has just ended
+fn in_game(state: Res<State<AppState>>) -> bool {
+    matches!(state.get(), AppState::Playing | AppState::GameOver)
+}
+
+// Встроенный шрифт Bevy не содержит кириллицы, заменяем его на DejaVu Sans
+fn install_font(mut fonts: ResMut<Assets<Font>>) {
+    let font = Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSans.ttf").to_vec())
+        .expect("the bundled font is valid");
+    fonts.insert(Handle::<Font>::default(), font);
+}
+
+/// Centre of the cell in world coordinates, rows above the board are allowed.
+/// The board is centred at the origin, the camera fits it into the window
+fn cell_position(board: &Board, x: u32, y: u32) -> Vec2 {
+    Vec2 {
+        x: (x as f32 + 0.5 - board.width() as f32 / 2.) * ELEMENT_SIZE,
+        y: (y as f32 + 0.5 - board.height() as f32 / 2.) * ELEMENT_SIZE,
+    }
+}
+
+fn piece_sprite(sprites: &PieceSprites, owner: ElementType, position: Vec2) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(Vec2 {
+                x: ELEMENT_SIZE,
+                y: ELEMENT_SIZE,
+            }),
+            ..default()
+        },
+        texture: sprites.get(owner),
+        transform: Transform::from_translation(position.extend(1.)),
+        ..default()
+    }
+}
+
+/// Size of the grid sprite for a board of this size
+fn grid_size(width: u32, height: u32) -> Vec2 {
+    Vec2 {
+        x: ELEMENT_SIZE * width as f32,
+        y: ELEMENT_SIZE * height as f32,
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+) {
+    // Положение и масштаб камеры выставляет `layout::fit_board`
+    commands.spawn(Camera2dBundle::default());
+    commands.insert_resource(PieceSprites([
+        asset_server.load("sprites/0.png"),
+        asset_server.load("sprites/1.png"),
+    ]));
+    let grid = Grid(GameState::new(settings.new_board()));
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(grid_size(grid.board().width(), grid.board().height())),
+                ..default()
+            },
+            texture: asset_server.load("sprites/grid.png"),
+            ..default()
+        },
+        ImageScaleMode::Tiled {
+            tile_x: true,
+            tile_y: true,
+            stretch_value: 1., // The image will tile every 128px
+        },
+        grid,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 20.,
+                    color: YELLOW,
+                    ..default()
+                },
+            )
+            .with_justify(JustifyText::Center),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        HintText,
+    ));
+}
+
+// Получаем координаты курсора или пальца и сохраняем как ресурс
+fn get_cursor_world_pos(
+    mut cursor_world_pos: ResMut<CursorWorldPos>,
+    mut active_touch: ResMut<ActiveTouch>,
+    touches: Res<Touches>,
+    q_primary_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    let primary_window = q_primary_window.single();
+    let (main_camera, main_camera_transform) = q_camera.single();
+    if active_touch.0.is_none() {
+        active_touch.0 = touches.iter_just_pressed().next().map(|touch| touch.id());
+    }
+    // Пока палец на экране, он ведёт выбор столбца, как курсор мыши
+    let touch = active_touch
+        .0
+        .and_then(|id| touches.get_pressed(id).or_else(|| touches.get_released(id)));
+    let position = touch
+        .map(|touch| touch.position())
+        .or_else(|| primary_window.cursor_position())
+        .and_then(|cursor_pos| main_camera.viewport_to_world_2d(main_camera_transform, cursor_pos));
+    // Ресурс меняется только вместе с курсором, иначе мышь перебивала бы выбор с клавиатуры
+    if cursor_world_pos.0 != position {
+        cursor_world_pos.0 = position;
+    }
+}
+
+/// Column under the cursor, `None` outside of the board
+fn cursor_column(cursor_world_pos: &CursorWorldPos, board: &Board) -> Option<u32> {
+    let right_up_corner = grid_size(board.width(), board.height()) / 2.;
+    let left_down_corner = -right_up_corner;
+    let mouse_pos = cursor_world_pos.0?;
+    if (mouse_pos.x > left_down_corner.x && mouse_pos.x < right_up_corner.x)
+        && (mouse_pos.y > left_down_corner.y && mouse_pos.y < right_up_corner.y)
+    {
+        let x_pos = (mouse_pos - left_down_corner).x;
+        Some((x_pos / ELEMENT_SIZE) as u32)
+    } else {
+        None
+    }
+}
+
+fn check_mouse_pos(
+    cursor_world_pos: Res<CursorWorldPos>,
+    mut column: ResMut<Column>,
+    q_grid: Query<&Grid>,
+) {
+    if let Ok(grid) = q_grid.get_single() {
+        column.0 = cursor_column(&cursor_world_pos, grid.board());
+    }
+}
+
+// Клик мышью или касание бросает фишку в столбец под курсором, если сейчас ход
+// человека. Клик мимо поля, например по кнопкам, фишку не бросает
+fn click_column(
+    cursor_world_pos: Res<CursorWorldPos>,
+    mut column: ResMut<Column>,
+    opponent: Res<Opponent>,
+    q_grid: Query<&Grid>,
+    mut drops: EventWriter<DropPiece>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    column.0 = cursor_column(&cursor_world_pos, grid.board());
+    if let Some(column) = column.0 {
+        if opponent.is_local(grid.turn()) {
+            drops.send(DropPiece { column });
+        }
+    }
+}
+
+/// Run condition, true when the finger that moves the selection is lifted.
+/// Other fingers never drop a piece, so multi-touch cannot drop twice
+fn touch_released(touches: Res<Touches>, active_touch: Res<ActiveTouch>) -> bool {
+    active_touch.0.is_some_and(|id| touches.just_released(id))
+}
+
+// Когда палец убран или касание отменено, выбор снова ведёт мышь
+fn end_touch(touches: Res<Touches>, mut active_touch: ResMut<ActiveTouch>) {
+    if let Some(id) = active_touch.0 {
+        if touches.get_pressed(id).is_none() {
+            active_touch.0 = None;
+        }
+    }
+}
+
+// Фишка появляется над столбцом и падает, до приземления ввод не принимается
+fn spawn_element(
+    mut commands: Commands,
+    mut drops: EventReader<DropPiece>,
+    mut q_grid: Query<&mut Grid>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    sprites: Res<PieceSprites>,
+) {
+    let Ok(mut grid) = q_grid.get_single_mut() else {
+        return;
+    };
+    let Some(drop) = drops.read().last() else {
+        return;
+    };
+    let played = match history.play(&mut grid, drop.column) {
+        Ok(played) => played,
+        Err(err) => {
+            // Ход не засчитан, очередь остаётся у того же игрока. Полный столбец
+            // и так виден по красной фишке над ним, поэтому только пишем в лог
+            info!("move rejected: {}", err);
+            return;
+        }
+    };
+    let board = grid.board();
+    let start = cell_position(board, played.column, board.height());
+    let end = cell_position(board, played.column, played.row);
+    commands.spawn((
+        piece_sprite(&sprites, played.player, start),
+        Piece {
+            pos: UVec2::new(played.column, played.row),
+            owner: played.player,
+        },
+        Falling::new(start.y, end.y),
+    ));
+    changes.send(BoardChanged);
+}
+
+// Победу и ничью объявляем, только когда фишка легла на место
+fn finish_move(
+    mut landed: EventReader<PieceLanded>,
+    q_grid: Query<&Grid>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if landed.is_empty() {
+        return;
+    }
+    landed.clear();
+    if q_grid.get_single().is_ok_and(|grid| grid.is_over()) {
+        next_state.set(AppState::GameOver);
+    }
+}
+
+// Запускаем поиск хода компьютера в фоне, чтобы не останавливать отрисовку
+fn start_ai(
+    mut commands: Commands,
+    opponent: Res<Opponent>,
+    task: Option<Res<AiTask>>,
+    q_grid: Query<&Grid>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let OpponentKind::Computer(config) = &opponent.kind else {
+        return;
+    };
+    if task.is_some() || opponent.side != Some(grid.turn()) || grid.is_over() {
+        return;
+    }
+    let game = grid.0.clone();
+    let config = config.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { ai::best_move(&game, &config) });
+    commands.insert_resource(AiTask(task));
+}
+
+fn poll_ai(
+    mut commands: Commands,
+    mut task: ResMut<AiTask>,
+    mut drops: EventWriter<DropPiece>,
+) {
+    if let Some(column) = block_on(future::poll_once(&mut task.0)) {
+        commands.remove_resource::<AiTask>();
+        if let Some(column) = column {
+            drops.send(DropPiece { column });
+        }
+    }
+}
+
+// Enter — игра вдвоём, C — против компьютера
+fn choose_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut opponent: ResMut<Opponent>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keys.just_pressed(KeyCode::Enter) {
+        opponent.side = None;
+    } else if keys.just_pressed(KeyCode::KeyC) {
+        opponent.side = Some(ElementType::Blue);
+    } else {
+        return;
+    }
+    next_state.set(AppState::Playing);
+}
+
+// Очищаем поле и начинаем заново, красные ходят первыми
+fn new_game(
+    mut commands: Commands,
+    mut q_grid: Query<&mut Grid>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if let Ok(mut grid) = q_grid.get_single_mut() {
+        grid.reset();
+    }
+    history.clear();
+    changes.send(BoardChanged);
+    // Ход, который компьютер считал для старой партии, больше не нужен
+    commands.remove_resource::<AiTask>();
+    next_state.set(AppState::Playing);
+}
+
+/// Run condition for a shortcut pressed together with Ctrl
+fn ctrl_just_pressed(key: KeyCode) -> impl FnMut(Res<ButtonInput<KeyCode>>) -> bool + Clone {
+    move |keys: Res<ButtonInput<KeyCode>>| {
+        keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) && keys.just_pressed(key)
+    }
+}
+
+fn history_keys(keys: Res<ButtonInput<KeyCode>>, mut commands: EventWriter<HistoryCommand>) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyZ) {
+        commands.send(HistoryCommand::Undo);
+    } else if keys.just_pressed(KeyCode::KeyY) {
+        commands.send(HistoryCommand::Redo);
+    }
+}
+
+fn press_history_button(
+    q_buttons: Query<(&Interaction, &HistoryButton), Changed<Interaction>>,
+    mut commands: EventWriter<HistoryCommand>,
+) {
+    for (interaction, button) in q_buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            commands.send(button.0);
+        }
+    }
+}
+
+// Против компьютера отменяем и его ответ, чтобы ход вернулся к человеку
+fn apply_history(
+    mut commands: Commands,
+    mut history_commands: EventReader<HistoryCommand>,
+    mut history: ResMut<History>,
+    mut q_grid: Query<&mut Grid>,
+    opponent: Res<Opponent>,
+    mut changes: EventWriter<BoardChanged>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Ok(mut grid) = q_grid.get_single_mut() else {
+        return;
+    };
+    for command in history_commands.read() {
+        let changed = match command {
+            HistoryCommand::Undo => {
+                let undone = history.undo(&mut grid).is_some();
+                if undone && opponent.side == Some(grid.turn()) {
+                    history.undo(&mut grid);
+                }
+                undone
+            }
+            HistoryCommand::Redo => {
+                let redone = history.redo(&mut grid).is_some();
+                if redone && opponent.side == Some(grid.turn()) && !grid.is_over() {
+                    history.redo(&mut grid);
+                }
+                redone
+            }
+        };
+        if !changed {
+            continue;
+        }
+        // Компьютер мог считать ход для позиции, которой больше нет
+        commands.remove_resource::<AiTask>();
+        changes.send(BoardChanged);
+        next_state.set(if grid.is_over() {
+            AppState::GameOver
+        } else {
+            AppState::Playing
+        });
+    }
+}
+
+fn save_game(
+    q_grid: Query<&Grid>,
+    history: Res<History>,
+    settings: Res<GameSettings>,
+    opponent: Res<Opponent>,
+    locale: Res<Locale>,
+    mut notices: EventWriter<Notice>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let mut record = GameRecord::new(&settings, &history, grid);
+    if let (Some(side), OpponentKind::Computer(_)) = (opponent.side, &opponent.kind) {
+        *record.name_mut(side) = "Computer".to_string();
+    }
+    notices.send(Notice(match record.save(SAVE_PATH) {
+        Ok(()) => locale.format("record.saved", &[("path", &SAVE_PATH)]),
+        Err(err) => locale.format("record.save_failed", &[("error", &locale.error(&err))]),
+    }));
+}
+
+fn load_game(
+    mut commands: Commands,
+    mut q_grid: Query<(&mut Grid, &mut Sprite)>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    mut next_state: ResMut<NextState<AppState>>,
+    locale: Res<Locale>,
+    mut notices: EventWriter<Notice>,
+) {
+    let record = match GameRecord::load(SAVE_PATH) {
+        Ok(record) => record,
+        Err(err) => {
+            notices.send(Notice(
+                locale.format("record.load_failed", &[("error", &locale.error(&err))]),
+            ));
+            return;
+        }
+    };
+    if let Ok((mut grid, mut sprite)) = q_grid.get_single_mut() {
+        let applied = apply_record(
+            &record,
+            &mut commands,
+            &mut grid,
+            &mut sprite,
+            &mut history,
+            &mut changes,
+            &mut next_state,
+        );
+        if let Err(err) = applied {
+            notices.send(Notice(
+                locale.format("record.replay_failed", &[("error", &locale.error(&err))]),
+            ));
+        }
+    }
+}
+
+fn apply_start_record(
+    mut commands: Commands,
+    record: Res<StartRecord>,
+    mut q_grid: Query<(&mut Grid, &mut Sprite)>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    mut next_state: ResMut<NextState<AppState>>,
+    locale: Res<Locale>,
+    mut notices: EventWriter<Notice>,
+) {
+    if let Ok((mut grid, mut sprite)) = q_grid.get_single_mut() {
+        let applied = apply_record(
+            &record.0,
+            &mut commands,
+            &mut grid,
+            &mut sprite,
+            &mut history,
+            &mut changes,
+            &mut next_state,
+        );
+        if let Err(err) = applied {
+            notices.send(Notice(
+                locale.format("record.replay_failed", &[("error", &locale.error(&err))]),
+            ));
+        }
+    }
+    commands.remove_resource::<StartRecord>();
+}
+
+// Восстанавливаем поле, историю и очередь хода из записи партии
+fn apply_record(
+    record: &GameRecord,
+    commands: &mut Commands,
+    grid: &mut Grid,
+    sprite: &mut Sprite,
+    history: &mut History,
+    changes: &mut EventWriter<BoardChanged>,
+    next_state: &mut NextState<AppState>,
+) -> Result<(), ElemError> {
+    let (game, moves) = record.replay()?;
+    grid.0 = game;
+    history.0 = moves;
+    sprite.custom_size = Some(grid_size(record.settings.width, record.settings.height));
+    commands.insert_resource(GameSettings(record.settings.clone()));
+    commands.remove_resource::<AiTask>();
+    changes.send(BoardChanged);
+    next_state.set(if grid.is_over() {
+        AppState::GameOver
+    } else {
+        AppState::Playing
+    });
+    Ok(())
+}
+
+// P в меню открывает сохранённую партию, после игры — только что сыгранную
+fn open_replay(
+    mut commands: Commands,
+    state: Res<State<AppState>>,
+    q_grid: Query<&Grid>,
+    history: Res<History>,
+    settings: Res<GameSettings>,
+    mut next_state: ResMut<NextState<AppState>>,
+    locale: Res<Locale>,
+    mut notices: EventWriter<Notice>,
+) {
+    let record = if *state.get() == AppState::Menu {
+        match GameRecord::load(SAVE_PATH) {
+            Ok(record) => record,
+            Err(err) => {
+                notices.send(Notice(
+                    locale.format("record.load_failed", &[("error", &locale.error(&err))]),
+                ));
+                return;
+            }
+        }
+    } else {
+        let Ok(grid) = q_grid.get_single() else {
+            return;
+        };
+        GameRecord::new(&settings, &history, grid)
+    };
+    commands.insert_resource(Replay::new(record));
+    next_state.set(AppState::Replay);
+}
+
+// H включает и выключает подсказки решателя
+fn toggle_hints(mut hints: ResMut<Hints>) {
+    hints.enabled = !hints.enabled;
+}
+
+// Решаем текущую позицию в фоне, старый поиск останавливаем
+fn start_hints(
+    mut commands: Commands,
+    hints: Res<Hints>,
+    task: Option<Res<HintTask>>,
+    q_grid: Query<&Grid>,
+    state: Res<State<AppState>>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let key = grid.board().bits().key();
+    let wanted = hints.enabled && *state.get() == AppState::Playing && hints.key != Some(key);
+    if let Some(task) = &task {
+        if wanted && task.key == key {
+            return;
+        }
+        task.stop.store(true, Ordering::Relaxed);
+        commands.remove_resource::<HintTask>();
+    }
+    if !wanted {
+        return;
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let solver = hints.solver.clone();
+    let game = grid.0.clone();
+    let task_stop = stop.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        // Остановленный поиск скоро отпустит решатель
+        let mut solver = solver.lock().unwrap();
+        let solver = solver.get_or_insert_with(Solver::new);
+        solver.set_stop(task_stop);
+        solver.analyze(&game)
+    });
+    commands.insert_resource(HintTask { key, stop, task });
+}
+
+fn poll_hints(mut commands: Commands, mut task: ResMut<HintTask>, mut hints: ResMut<Hints>) {
+    if let Some(columns) = block_on(future::poll_once(&mut task.task)) {
+        hints.key = Some(task.key);
+        hints.columns = columns;
+        commands.remove_resource::<HintTask>();
+    }
+}
+
+fn show_hint(
+    hints: Res<Hints>,
+    column: Res<Column>,
+    state: Res<State<AppState>>,
+    q_grid: Query<&Grid>,
+    mut q_hint: Query<(&mut Text, &mut Transform, &mut Visibility), With<HintText>>,
+    locale: Res<Locale>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = q_hint.get_single_mut() else {
+        return;
+    };
+    let (Ok(grid), Some(column)) = (q_grid.get_single(), column.0) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    if !hints.enabled || *state.get() != AppState::Playing {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    let ready = hints.key == Some(grid.board().bits().key());
+    text.sections[0].value = match hints.columns.get(column as usize) {
+        _ if !ready => "…".to_string(),
+        Some(Some(Solution::Win { plies })) => {
+            locale.format("hint.win", &[("moves", &((plies + 1) / 2))])
+        }
+        Some(Some(Solution::Loss { plies })) => {
+            locale.format("hint.loss", &[("moves", &((plies + 1) / 2))])
+        }
+        Some(Some(Solution::Draw)) => locale.get("hint.draw").to_string(),
+        _ => String::new(),
+    };
+    let board = grid.board();
+    transform.translation = cell_position(board, column, board.height()).extend(2.);
+    *visibility = Visibility::Visible;
+}
+
+fn show_menu(mut commands: Commands, locale: Res<Locale>) {
+    spawn_overlay(&mut commands, locale.get("menu"));
+}
+
+fn show_game_over(mut commands: Commands, q_grid: Query<&Grid>, locale: Res<Locale>) {
+    let text = match q_grid.get_single().ok().and_then(|grid| grid.outcome()) {
+        Some(Outcome::Win(player)) => {
+            locale.format("game_over.win", &[("player", &locale.player(player))])
+        }
+        _ => locale.get("game_over.draw").to_string(),
+    };
+    spawn_overlay(
+        &mut commands,
+        &format!("{}\n{}", text, locale.get("game_over.keys")),
+    );
+}
+
+fn spawn_overlay(commands: &mut Commands, text: &str) {
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font_size: 40.,
+                color: YELLOW,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(16.),
+            left: Val::Px(16.),
+            ..default()
+        }),
+        Overlay,
+    ));
+}
+
+fn despawn_overlay(mut commands: Commands, q_overlay: Query<Entity, With<Overlay>>) {
+    for entity in q_overlay.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Фишки не пересоздаются: при изменении поля добавляем новые, убираем снятые
+// и переставляем остальные, например после смены размера поля
+fn draw(
+    mut commands: Commands,
+    mut changes: EventReader<BoardChanged>,
+    q_grid: Query<&Grid>,
+    mut q_pieces: Query<(Entity, &mut Piece, &mut Handle<Image>, &mut Transform)>,
+    q_falling: Query<(), With<Falling>>,
+    sprites: Res<PieceSprites>,
+) {
+    if changes.is_empty() {
+        return;
+    }
+    changes.clear();
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let board = grid.board();
+    let mut shown = HashSet::new();
+    for (entity, mut piece, mut texture, mut transform) in q_pieces.iter_mut() {
+        let Ok(owner) = board.get(&Pos::new(piece.pos.x, piece.pos.y)) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if piece.owner != owner {
+            piece.owner = owner;
+            *texture = sprites.get(owner);
+        }
+        // Падающую фишку двигает анимация
+        if !q_falling.contains(entity) {
+            let position = cell_position(board, piece.pos.x, piece.pos.y);
+            transform.translation = position.extend(1.);
+        }
+        shown.insert(piece.pos);
+    }
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let pos = UVec2::new(x, y);
+            if shown.contains(&pos) {
+                continue;
+            }
+            if let Ok(owner) = board.get(&Pos::new(x, y)) {
+                commands.spawn((
+                    piece_sprite(&sprites, owner, cell_position(board, x, y)),
+                    Piece { pos, owner },
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Pieces falling into place after a move.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use super::ELEMENT_SIZE;
+
+/// Time to fall by one cell, longer falls take `sqrt(cells)` times more
+const FALL_TIME: f32 = 0.12;
+const BOUNCE_TIME: f32 = 0.15;
+const BOUNCE_HEIGHT: f32 = ELEMENT_SIZE * 0.15;
+
+/// A piece on its way from above the column to the cell it was dropped into
+#[derive(Component)]
+pub(super) struct Falling {
+    from: f32,
+    to: f32,
+    fall_time: f32,
+    elapsed: f32,
+}
+
+/// Sent when the falling piece reaches its cell
+#[derive(Event)]
+pub(super) struct PieceLanded;
+
+impl Falling {
+    pub fn new(from: f32, to: f32) -> Falling {
+        let cells = ((from - to) / ELEMENT_SIZE).max(1.);
+        Falling {
+            from,
+            to,
+            fall_time: FALL_TIME * cells.sqrt(),
+            elapsed: 0.,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.fall_time + BOUNCE_TIME
+    }
+
+    // Разгоняется, как под действием тяжести, и немного подпрыгивает в ячейке
+    fn height(&self) -> f32 {
+        if self.elapsed < self.fall_time {
+            let t = self.elapsed / self.fall_time;
+            self.from + (self.to - self.from) * t * t
+        } else if !self.is_done() {
+            let t = (self.elapsed - self.fall_time) / BOUNCE_TIME;
+            self.to + BOUNCE_HEIGHT * (PI * t).sin()
+        } else {
+            self.to
+        }
+    }
+}
+
+/// Run condition, true when no piece is in the air
+pub(super) fn pieces_settled(q_falling: Query<(), With<Falling>>) -> bool {
+    q_falling.is_empty()
+}
+
+pub(super) fn fall(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_falling: Query<(Entity, &mut Falling, &mut Transform)>,
+    mut landed: EventWriter<PieceLanded>,
+) {
+    for (entity, mut falling, mut transform) in q_falling.iter_mut() {
+        falling.elapsed += time.delta_seconds();
+        transform.translation.y = falling.height();
+        if falling.is_done() {
+            // Дальше фишку переставляет `draw` вместе с остальными
+            commands.entity(entity).remove::<Falling>();
+            landed.send(PieceLanded);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Choosing the column and dropping a piece with the keyboard or a gamepad.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use super::{animation::pieces_settled, AppState, Column, DropPiece, Grid, Opponent};
+
+pub(super) struct ControlsPlugin;
+
+/// How far the stick has to be tilted to move the selection by one column
+const STICK_PRESS: f32 = 0.5;
+/// The stick has to come back this close to the centre before the next step
+const STICK_RELEASE: f32 = 0.3;
+
+const DIGITS: [(KeyCode, KeyCode); 9] = [
+    (KeyCode::Digit1, KeyCode::Numpad1),
+    (KeyCode::Digit2, KeyCode::Numpad2),
+    (KeyCode::Digit3, KeyCode::Numpad3),
+    (KeyCode::Digit4, KeyCode::Numpad4),
+    (KeyCode::Digit5, KeyCode::Numpad5),
+    (KeyCode::Digit6, KeyCode::Numpad6),
+    (KeyCode::Digit7, KeyCode::Numpad7),
+    (KeyCode::Digit8, KeyCode::Numpad8),
+    (KeyCode::Digit9, KeyCode::Numpad9),
+];
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                keyboard_column,
+                gamepad_column,
+                drop_selected.run_if(drop_pressed).run_if(pieces_settled),
+            )
+                .chain()
+                .run_if(in_state(AppState::Playing))
+                .after(super::check_mouse_pos)
+                .before(super::spawn_element),
+        );
+    }
+}
+
+/// Moves the selection by `step` columns, from the centre when nothing is selected
+fn step_column(column: &mut Column, width: u32, step: i32) {
+    let next = match column.0 {
+        Some(current) => (current as i32 + step).clamp(0, width as i32 - 1) as u32,
+        None => width / 2,
+    };
+    column.0 = Some(next);
+}
+
+// Стрелки или A/D сдвигают выбор, цифры выбирают столбец сразу
+fn keyboard_column(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut column: ResMut<Column>,
+    q_grid: Query<&Grid>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let width = grid.board().width();
+    if keys.any_just_pressed([KeyCode::ArrowLeft, KeyCode::KeyA]) {
+        step_column(&mut column, width, -1);
+    }
+    if keys.any_just_pressed([KeyCode::ArrowRight, KeyCode::KeyD]) {
+        step_column(&mut column, width, 1);
+    }
+    for (index, (digit, numpad)) in DIGITS.into_iter().enumerate() {
+        if index < width as usize && keys.any_just_pressed([digit, numpad]) {
+            column.0 = Some(index as u32);
+        }
+    }
+}
+
+// Крестовина сдвигает выбор по нажатию, стик — по наклону, после которого
+// его нужно вернуть к центру
+fn gamepad_column(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut tilted: Local<HashSet<Gamepad>>,
+    mut column: ResMut<Column>,
+    q_grid: Query<&Grid>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let width = grid.board().width();
+    for gamepad in gamepads.iter() {
+        if buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadLeft)) {
+            step_column(&mut column, width, -1);
+        }
+        if buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::DPadRight)) {
+            step_column(&mut column, width, 1);
+        }
+        let x = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+            .unwrap_or_default();
+        if x.abs() < STICK_RELEASE {
+            tilted.remove(&gamepad);
+        } else if x.abs() > STICK_PRESS && tilted.insert(gamepad) {
+            step_column(&mut column, width, x.signum() as i32);
+        }
+    }
+}
+
+/// Run condition for Space, Enter or the South button of any gamepad
+fn drop_pressed(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+) -> bool {
+    keys.any_just_pressed([KeyCode::Space, KeyCode::Enter, KeyCode::NumpadEnter])
+        || gamepads.iter().any(|gamepad| {
+            buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::South))
+        })
+}
+
+// Бросок идёт тем же событием, что и клик мышью
+fn drop_selected(
+    column: Res<Column>,
+    opponent: Res<Opponent>,
+    q_grid: Query<&Grid>,
+    mut drops: EventWriter<DropPiece>,
+) {
+    let (Some(column), Ok(grid)) = (column.0, q_grid.get_single()) else {
+        return;
+    };
+    if opponent.is_local(grid.turn()) {
+        drops.send(DropPiece { column });
+    }
+}
@@ -0,0 +1,107 @@
+//! Marking the winning lines once the game is won.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use connect_four::Pos;
+
+use super::{animation::Falling, cell_position, AppState, Grid, Piece, ELEMENT_SIZE, YELLOW};
+
+pub(super) struct HighlightPlugin;
+
+/// Cells of every winning line and the cells at both ends of each line
+#[derive(Resource)]
+struct WinningLines {
+    cells: HashSet<UVec2>,
+    lines: Vec<(UVec2, UVec2)>,
+}
+
+/// Colour of the pieces that are not part of a winning line
+const DIMMED: Color = Color::rgb(0.35, 0.35, 0.35);
+const PULSE_SPEED: f32 = 6.;
+const PULSE_SCALE: f32 = 0.08;
+
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameOver), find_winning_lines)
+            .add_systems(OnExit(AppState::GameOver), clear_highlight)
+            .add_systems(
+                Update,
+                (highlight_pieces, draw_winning_lines)
+                    .run_if(resource_exists::<WinningLines>)
+                    .after(super::draw),
+            );
+    }
+}
+
+// Одним ходом можно собрать сразу несколько линий, отмечаем все
+fn find_winning_lines(mut commands: Commands, q_grid: Query<&Grid>) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let matches = grid.board().get_matches();
+    if matches.is_empty() {
+        return;
+    }
+    let to_cell = |pos: Pos| UVec2::new(pos.x, pos.y);
+    commands.insert_resource(WinningLines {
+        cells: matches
+            .without_duplicates()
+            .into_iter()
+            .map(to_cell)
+            .collect(),
+        lines: matches
+            .iter()
+            .filter_map(|line| line.ends())
+            .map(|(start, end)| (to_cell(start), to_cell(end)))
+            .collect(),
+    });
+}
+
+// Фишки победной линии пульсируют, остальные затемнены
+fn highlight_pieces(
+    time: Res<Time>,
+    winning: Res<WinningLines>,
+    mut q_pieces: Query<(&Piece, &mut Sprite, &mut Transform), Without<Falling>>,
+) {
+    let pulse = 1. + PULSE_SCALE * (time.elapsed_seconds() * PULSE_SPEED).sin();
+    for (piece, mut sprite, mut transform) in q_pieces.iter_mut() {
+        if winning.cells.contains(&piece.pos) {
+            sprite.color = Color::WHITE;
+            transform.scale = Vec3::splat(pulse);
+        } else {
+            sprite.color = DIMMED;
+        }
+    }
+}
+
+fn draw_winning_lines(
+    winning: Res<WinningLines>,
+    q_grid: Query<&Grid>,
+    mut gizmos: Gizmos,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    let board = grid.board();
+    for (start, end) in &winning.lines {
+        let start = cell_position(board, start.x, start.y);
+        let end = cell_position(board, end.x, end.y);
+        gizmos.line_2d(start, end, YELLOW);
+    }
+    for cell in &winning.cells {
+        let center = cell_position(board, cell.x, cell.y);
+        gizmos.circle_2d(center, ELEMENT_SIZE * 0.45, YELLOW);
+    }
+}
+
+fn clear_highlight(
+    mut commands: Commands,
+    mut q_pieces: Query<(&mut Sprite, &mut Transform), With<Piece>>,
+) {
+    commands.remove_resource::<WinningLines>();
+    for (mut sprite, mut transform) in q_pieces.iter_mut() {
+        sprite.color = Color::WHITE;
+        transform.scale = Vec3::ONE;
+    }
+}
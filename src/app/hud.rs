@@ -0,0 +1,345 @@
+//! Status bar with the turn, move count, result, session score and the
+//! number of spectators of a network game, and the line with notices.
+
+use bevy::{app::AppExit, prelude::*};
+use connect_four::{ElementType, Outcome};
+
+use super::{
+    animation::PieceLanded, in_game, AppState, BoardChanged, Grid, HistoryButton, HistoryCommand,
+    Locale, NewGame, Notice, Spectators,
+};
+
+pub(super) struct HudPlugin;
+
+/// Games finished in this session, a game counts when its last piece lands
+#[derive(Resource, Default)]
+struct Score {
+    red: u32,
+    blue: u32,
+    draws: u32,
+}
+
+#[derive(Component)]
+struct Hud;
+
+/// Square in the colour of the player to move or of the winner
+#[derive(Component)]
+struct TurnSwatch;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+struct MovesText;
+
+#[derive(Component)]
+struct ScoreText;
+
+/// Empty while nobody watches
+#[derive(Component)]
+struct SpectatorsText;
+
+/// The last notice, stays on the screen in every state until the timer ends
+#[derive(Component)]
+struct NoticeText;
+
+/// Time left until the notice is cleared
+#[derive(Resource)]
+struct NoticeTimer(Timer);
+
+/// How long a notice stays on the screen
+const NOTICE_SECONDS: f32 = 4.;
+
+#[derive(Component, Clone, Copy)]
+enum HudButton {
+    NewGame,
+    History(HistoryCommand),
+    Quit,
+}
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .insert_resource(NoticeTimer(Timer::from_seconds(
+                NOTICE_SECONDS,
+                TimerMode::Once,
+            )))
+            .add_systems(Startup, spawn_hud)
+            .add_systems(
+                Update,
+                (
+                    count_result,
+                    press_hud_button,
+                    show_hud.run_if(state_changed::<AppState>),
+                    update_status
+                        .run_if(on_event::<BoardChanged>().or_else(state_changed::<AppState>)),
+                    update_score.run_if(resource_changed::<Score>),
+                    update_spectators.run_if(resource_changed::<Spectators>),
+                )
+                    .chain()
+                    .after(super::finish_move),
+            )
+            .add_systems(Update, (show_notice, clear_notice).chain());
+    }
+}
+
+fn spawn_hud(mut commands: Commands, locale: Res<Locale>) {
+    let text_style = TextStyle {
+        font_size: 20.,
+        color: Color::WHITE,
+        ..default()
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                // Над строкой состояния, чтобы не закрывать надписи поверх доски
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(56.),
+                left: Val::Px(16.),
+                right: Val::Px(16.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", text_style.clone()), NoticeText));
+        });
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(16.),
+                    left: Val::Px(16.),
+                    right: Val::Px(16.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(16.),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(20.),
+                                height: Val::Px(20.),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        TurnSwatch,
+                    ));
+                    parent.spawn((TextBundle::from_section("", text_style.clone()), StatusText));
+                    parent.spawn((TextBundle::from_section("", text_style.clone()), MovesText));
+                    parent.spawn((TextBundle::from_section("", text_style.clone()), ScoreText));
+                    parent.spawn((
+                        TextBundle::from_section("", text_style.clone()),
+                        SpectatorsText,
+                    ));
+                });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (HudButton::NewGame, "hud.new_game"),
+                        (HudButton::History(HistoryCommand::Undo), "hud.undo"),
+                        (HudButton::History(HistoryCommand::Redo), "hud.redo"),
+                        (HudButton::Quit, "hud.quit"),
+                    ] {
+                        let mut entity = parent.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::all(Val::Px(8.)),
+                                    ..default()
+                                },
+                                background_color: Color::DARK_GRAY.into(),
+                                ..default()
+                            },
+                            button,
+                        ));
+                        if let HudButton::History(command) = button {
+                            entity.insert(HistoryButton(command));
+                        }
+                        entity.with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                locale.get(label),
+                                text_style.clone(),
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+// Отмена и повтор обрабатываются вместе с Ctrl+Z и Ctrl+Y через `HistoryButton`
+fn press_hud_button(
+    q_buttons: Query<(&Interaction, &HudButton), Changed<Interaction>>,
+    mut new_game: EventWriter<NewGame>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button) in q_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            HudButton::NewGame => {
+                new_game.send(NewGame);
+            }
+            HudButton::History(_) => {}
+            HudButton::Quit => {
+                exit.send(AppExit);
+            }
+        }
+    }
+}
+
+// Засчитываем только доигранные партии, а не возврат к концу через историю
+fn count_result(
+    mut landed: EventReader<PieceLanded>,
+    q_grid: Query<&Grid>,
+    mut score: ResMut<Score>,
+) {
+    if landed.is_empty() {
+        return;
+    }
+    landed.clear();
+    match q_grid.get_single().ok().and_then(|grid| grid.outcome()) {
+        Some(Outcome::Win(ElementType::Red)) => score.red += 1,
+        Some(Outcome::Win(ElementType::Blue)) => score.blue += 1,
+        Some(Outcome::Draw) => score.draws += 1,
+        None => {}
+    }
+}
+
+// Строка видна во время игры, в меню и при просмотре записи её нет
+fn show_hud(state: Res<State<AppState>>, mut q_hud: Query<&mut Visibility, With<Hud>>) {
+    if let Ok(mut visibility) = q_hud.get_single_mut() {
+        *visibility = if in_game(state) {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_status(
+    state: Res<State<AppState>>,
+    q_grid: Query<&Grid>,
+    mut q_swatch: Query<&mut BackgroundColor, With<TurnSwatch>>,
+    mut q_status: Query<&mut Text, (With<StatusText>, Without<MovesText>)>,
+    mut q_moves: Query<&mut Text, (With<MovesText>, Without<StatusText>)>,
+    locale: Res<Locale>,
+) {
+    let Ok(grid) = q_grid.get_single() else {
+        return;
+    };
+    // Итог показываем, когда последняя фишка уже легла
+    let outcome = grid
+        .outcome()
+        .filter(|_| *state.get() == AppState::GameOver);
+    let (status, swatch) = match outcome {
+        Some(Outcome::Win(player)) => (
+            locale.format("result.win", &[("player", &locale.player(player))]),
+            Some(player),
+        ),
+        Some(Outcome::Draw) => (locale.get("result.draw").to_string(), None),
+        None => (
+            locale.format("hud.turn", &[("player", &locale.player(grid.turn()))]),
+            Some(grid.turn()),
+        ),
+    };
+    if let Ok(mut text) = q_status.get_single_mut() {
+        text.sections[0].value = status;
+    }
+    if let Ok(mut color) = q_swatch.get_single_mut() {
+        *color = swatch.map_or(Color::GRAY, player_color).into();
+    }
+    if let Ok(mut text) = q_moves.get_single_mut() {
+        text.sections[0].value = locale.format("hud.moves", &[("count", &grid.board().len())]);
+    }
+}
+
+fn update_score(
+    score: Res<Score>,
+    mut q_score: Query<&mut Text, With<ScoreText>>,
+    locale: Res<Locale>,
+) {
+    if let Ok(mut text) = q_score.get_single_mut() {
+        text.sections[0].value = locale.format(
+            "hud.score",
+            &[
+                ("red", &score.red),
+                ("blue", &score.blue),
+                ("draws", &score.draws),
+            ],
+        );
+    }
+}
+
+fn update_spectators(
+    spectators: Res<Spectators>,
+    mut q_spectators: Query<&mut Text, With<SpectatorsText>>,
+    locale: Res<Locale>,
+) {
+    if let Ok(mut text) = q_spectators.get_single_mut() {
+        text.sections[0].value = match spectators.0 {
+            0 => String::new(),
+            count => locale.format("hud.spectators", &[("count", &count)]),
+        };
+    }
+}
+
+fn show_notice(
+    mut notices: EventReader<Notice>,
+    mut q_notice: Query<&mut Text, With<NoticeText>>,
+    mut timer: ResMut<NoticeTimer>,
+) {
+    // Из нескольких сообщений за кадр показываем последнее
+    let Some(Notice(message)) = notices.read().last() else {
+        return;
+    };
+    if let Ok(mut text) = q_notice.get_single_mut() {
+        text.sections[0].value = message.clone();
+    }
+    timer.0.reset();
+}
+
+fn clear_notice(
+    time: Res<Time>,
+    mut timer: ResMut<NoticeTimer>,
+    mut q_notice: Query<&mut Text, With<NoticeText>>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        if let Ok(mut text) = q_notice.get_single_mut() {
+            text.sections[0].value.clear();
+        }
+    }
+}
+
+fn player_color(player: ElementType) -> Color {
+    match player {
+        ElementType::Red => Color::RED,
+        ElementType::Blue => Color::BLUE,
+    }
+}
@@ -0,0 +1,75 @@
+//! Fitting the board into the window.
+//!
+//! The board lives at fixed world coordinates with its centre at the origin
+//! and cells of `ELEMENT_SIZE`. Only the camera follows the window: its scale
+//! and position are chosen so that the board and the row above it fill the
+//! free part of the window. Cursor and touch positions go through the same
+//! camera, so the column under them stays exact at any size or DPI.
+
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use super::{grid_size, replay::PANEL_WIDTH, AppState, BoardChanged, Grid, ELEMENT_SIZE};
+
+pub(super) struct LayoutPlugin;
+
+/// Free space around the board in logical pixels
+const MARGIN: f32 = 24.;
+/// Room for the status bar under the board during the game
+const HUD_HEIGHT: f32 = 56.;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            fit_board
+                .run_if(
+                    run_once()
+                        .or_else(on_event::<WindowResized>())
+                        .or_else(on_event::<BoardChanged>())
+                        .or_else(state_changed::<AppState>),
+                )
+                .after(super::draw),
+        );
+    }
+}
+
+// Поле вместе со строкой над ним, откуда падают фишки, вписываем в окно без
+// строки состояния и панели просмотра записи
+fn fit_board(
+    state: Res<State<AppState>>,
+    q_grid: Query<&Grid>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_camera: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
+) {
+    let (Ok(grid), Ok(window), Ok((mut transform, mut projection))) = (
+        q_grid.get_single(),
+        q_window.get_single(),
+        q_camera.get_single_mut(),
+    ) else {
+        return;
+    };
+    let (right, bottom) = match state.get() {
+        AppState::Playing | AppState::GameOver => (MARGIN, MARGIN + HUD_HEIGHT),
+        AppState::Replay => (MARGIN + PANEL_WIDTH, MARGIN),
+        AppState::Menu | AppState::Connecting => (MARGIN, MARGIN),
+    };
+    let free = Vec2::new(
+        window.width() - MARGIN - right,
+        window.height() - MARGIN - bottom,
+    );
+    // Свёрнутое окно имеет нулевой размер
+    if free.x <= 0. || free.y <= 0. {
+        return;
+    }
+    let board = grid.board();
+    let size = grid_size(board.width(), board.height() + 1);
+    let scale = (size / free).max_element();
+    projection.scale = scale;
+    // Центр свободной области смещён от центра окна на разницу отступов
+    let center = Vec2::new(0., ELEMENT_SIZE / 2.);
+    let offset = Vec2::new(right - MARGIN, MARGIN - bottom) / 2. * scale;
+    transform.translation = (center + offset).extend(transform.translation.z);
+}
@@ -0,0 +1,387 @@
+//! Playing against someone on another machine.
+//!
+//! The moves of the other player become `DropPiece` events, so they fall
+//! and land like the clicks at this screen. The other side is the
+//! `Opponent`, which already keeps local input to our own turn. A spectator
+//! gets the moves of both sides the same way and plays none of them.
+
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use bevy::{
+    app::AppExit, input::common_conditions::input_just_pressed, prelude::*,
+    utils::synccell::SyncCell,
+};
+use connect_four::{
+    net::{NetError, Session, SessionEvent, Spectator},
+    notation::GameRecord,
+    GameState, Outcome,
+};
+
+use super::{
+    animation::pieces_settled, apply_record, in_game, AppState, BoardChanged, DropPiece,
+    GameSettings, Grid, History, Locale, NewGame, Notice, Opponent, Overlay, Spectators,
+};
+
+pub(super) struct NetPlugin {
+    pub role: Role,
+}
+
+#[derive(Resource, Clone)]
+pub(super) enum Role {
+    /// Wait for the other player at the address
+    Host(String),
+    /// Connect to the player waiting at the address
+    Join(String),
+    /// Follow the game of the host at the address
+    Watch(String),
+}
+
+/// The session being set up in a background thread
+#[derive(Resource)]
+struct Connecting(SyncCell<Receiver<Result<Connection, NetError>>>);
+
+/// The connection to the other player
+#[derive(Resource)]
+pub(super) struct Link(SyncCell<Connection>);
+
+enum Connection {
+    Player(Session),
+    Spectator(Spectator),
+}
+
+/// Something that changed the game on both sides
+#[derive(Event)]
+struct LinkEvent(SessionEvent);
+
+/// The connection is broken, the game goes on at this screen
+#[derive(Event)]
+struct LinkLost(NetError);
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.role.clone())
+            .add_event::<LinkEvent>()
+            .add_event::<LinkLost>()
+            .add_systems(Startup, connect)
+            .add_systems(OnEnter(AppState::Connecting), show_connecting)
+            .add_systems(OnExit(AppState::Connecting), super::despawn_overlay)
+            .add_systems(
+                Update,
+                (
+                    finish_connecting.run_if(resource_exists::<Connecting>),
+                    poll_link
+                        .run_if(resource_exists::<Link>)
+                        .run_if(in_game)
+                        .run_if(pieces_settled),
+                    request_rematch
+                        .run_if(resource_exists::<Link>)
+                        .run_if(in_game)
+                        .run_if(input_just_pressed(KeyCode::KeyR).or_else(on_event::<NewGame>())),
+                    resign
+                        .run_if(resource_exists::<Link>)
+                        .run_if(in_state(AppState::Playing))
+                        .run_if(super::ctrl_just_pressed(KeyCode::KeyG)),
+                    follow_link
+                        .run_if(resource_exists::<Link>)
+                        .run_if(on_event::<LinkEvent>()),
+                    apply_sync
+                        .run_if(resource_exists::<Link>)
+                        .run_if(on_event::<LinkEvent>()),
+                    super::new_game.run_if(restarted),
+                )
+                    .chain()
+                    .before(super::spawn_element),
+            )
+            .add_systems(
+                Update,
+                (
+                    send_moves.run_if(resource_exists::<Link>),
+                    lose_link.run_if(on_event::<LinkLost>()),
+                )
+                    .chain()
+                    .after(super::spawn_element),
+            );
+    }
+}
+
+// Рукопожатие блокирует, поэтому ждём соперника в отдельном потоке
+fn connect(mut commands: Commands, role: Res<Role>, settings: Res<GameSettings>) {
+    let (sender, receiver) = mpsc::channel();
+    let role = role.clone();
+    let settings = settings.0.clone();
+    thread::spawn(move || {
+        let connection = match &role {
+            Role::Host(addr) => TcpListener::bind(addr)
+                .map_err(NetError::Io)
+                .and_then(|listener| Session::host(&listener, &settings))
+                .map(Connection::Player),
+            Role::Join(addr) => Session::join(addr.as_str()).map(Connection::Player),
+            Role::Watch(addr) => Spectator::watch(addr.as_str()).map(Connection::Spectator),
+        };
+        let _ = sender.send(connection);
+    });
+    commands.insert_resource(Connecting(SyncCell::new(receiver)));
+}
+
+fn show_connecting(mut commands: Commands, role: Res<Role>, locale: Res<Locale>) {
+    let text = match role.as_ref() {
+        Role::Host(addr) => locale.format("net.waiting", &[("address", addr)]),
+        Role::Join(addr) | Role::Watch(addr) => {
+            locale.format("net.connecting", &[("address", addr)])
+        }
+    };
+    super::spawn_overlay(&mut commands, &text);
+}
+
+fn finish_connecting(
+    mut commands: Commands,
+    mut connecting: ResMut<Connecting>,
+    mut opponent: ResMut<Opponent>,
+    mut events: EventWriter<LinkEvent>,
+    mut notices: EventWriter<Notice>,
+    mut q_overlay: Query<&mut Text, With<Overlay>>,
+    mut exit: EventWriter<AppExit>,
+    locale: Res<Locale>,
+) {
+    let connection = match connecting.0.get().try_recv() {
+        Ok(Ok(connection)) => connection,
+        Err(TryRecvError::Empty) => return,
+        // Ошибка остаётся на экране вместо ожидания, окно закрывает сам игрок
+        Ok(Err(err)) => {
+            commands.remove_resource::<Connecting>();
+            if let Ok(mut text) = q_overlay.get_single_mut() {
+                text.sections[0].value =
+                    locale.format("net.failed", &[("error", &locale.error(&err))]);
+            }
+            return;
+        }
+        Err(TryRecvError::Disconnected) => {
+            exit.send(AppExit);
+            return;
+        }
+    };
+    commands.remove_resource::<Connecting>();
+    match &connection {
+        Connection::Player(session) => {
+            let side = session.side();
+            notices.send(Notice(
+                locale.format("net.connected", &[("player", &locale.player(side))]),
+            ));
+            opponent.side = Some(side.other());
+        }
+        Connection::Spectator(_) => {
+            notices.send(Notice(locale.get("net.watching").to_string()));
+        }
+    }
+    commands.insert_resource(Link(SyncCell::new(connection)));
+    // Доска хозяина приходит при подключении так же, как при синхронизации
+    events.send(LinkEvent(SessionEvent::Synced));
+}
+
+// Ходы соперника бросаем тем же событием, что и клик мышью
+fn poll_link(
+    mut link: ResMut<Link>,
+    mut drops: EventWriter<DropPiece>,
+    mut events: EventWriter<LinkEvent>,
+    mut lost: EventWriter<LinkLost>,
+) {
+    match link.0.get().poll() {
+        Ok(Some(SessionEvent::Moved(played))) => {
+            drops.send(DropPiece {
+                column: played.column,
+            });
+        }
+        Ok(Some(event)) => {
+            events.send(LinkEvent(event));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            lost.send(LinkLost(err));
+        }
+    }
+}
+
+// Новая партия начинается, только когда её попросили обе стороны
+fn request_rematch(
+    mut link: ResMut<Link>,
+    mut events: EventWriter<LinkEvent>,
+    mut lost: EventWriter<LinkLost>,
+    mut notices: EventWriter<Notice>,
+    locale: Res<Locale>,
+) {
+    let Connection::Player(session) = link.0.get() else {
+        return;
+    };
+    match session.rematch() {
+        Ok(true) => {
+            events.send(LinkEvent(SessionEvent::Restarted));
+        }
+        Ok(false) => {
+            notices.send(Notice(locale.get("net.rematch_sent").to_string()));
+        }
+        Err(err) => {
+            lost.send(LinkLost(err));
+        }
+    }
+}
+
+fn resign(
+    mut link: ResMut<Link>,
+    mut q_grid: Query<&mut Grid>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut lost: EventWriter<LinkLost>,
+) {
+    let Connection::Player(session) = link.0.get() else {
+        return;
+    };
+    if let Err(err) = session.resign() {
+        lost.send(LinkLost(err));
+        return;
+    }
+    if let Ok(mut grid) = q_grid.get_single_mut() {
+        if grid.resign(session.side()).is_ok() {
+            next_state.set(AppState::GameOver);
+        }
+    }
+}
+
+fn follow_link(
+    mut events: EventReader<LinkEvent>,
+    mut link: ResMut<Link>,
+    mut q_grid: Query<&mut Grid>,
+    mut spectators: ResMut<Spectators>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut notices: EventWriter<Notice>,
+    locale: Res<Locale>,
+) {
+    let Ok(mut grid) = q_grid.get_single_mut() else {
+        return;
+    };
+    for LinkEvent(event) in events.read() {
+        match event {
+            // Сдался тот, кто проиграл партию в сессии
+            SessionEvent::Resigned => {
+                if let Some(Outcome::Win(winner)) = link.0.get().game().outcome() {
+                    if grid.resign(winner.other()).is_ok() {
+                        next_state.set(AppState::GameOver);
+                    }
+                }
+            }
+            SessionEvent::RematchOffered => {
+                notices.send(Notice(locale.get("net.rematch_offered").to_string()));
+            }
+            SessionEvent::Spectators(count) => {
+                spectators.0 = *count;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Гость играет на доске хозяина: её размер, ходы и очередь приходят от него
+fn apply_sync(
+    mut commands: Commands,
+    mut events: EventReader<LinkEvent>,
+    mut link: ResMut<Link>,
+    mut q_grid: Query<(&mut Grid, &mut Sprite)>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !events
+        .read()
+        .any(|LinkEvent(event)| *event == SessionEvent::Synced)
+    {
+        return;
+    }
+    let record = link.0.get().record();
+    if let Ok((mut grid, mut sprite)) = q_grid.get_single_mut() {
+        let applied = apply_record(
+            &record,
+            &mut commands,
+            &mut grid,
+            &mut sprite,
+            &mut history,
+            &mut changes,
+            &mut next_state,
+        );
+        // Сессия уже проверила каждый ход
+        if let Err(err) = applied {
+            error!("the game of the host cannot be played: {}", err);
+        }
+    }
+}
+
+/// Run condition for a rematch both sides agreed to
+fn restarted(mut events: EventReader<LinkEvent>) -> bool {
+    events
+        .read()
+        .any(|LinkEvent(event)| *event == SessionEvent::Restarted)
+}
+
+// Отправляем свои ходы, ходы соперника сессия уже знает
+fn send_moves(mut link: ResMut<Link>, history: Res<History>, mut lost: EventWriter<LinkLost>) {
+    let Connection::Player(session) = link.0.get() else {
+        return;
+    };
+    let known = session.history().len();
+    let Some(played) = history.moves().get(known..) else {
+        return;
+    };
+    for played in played {
+        if let Err(err) = session.play(played.column) {
+            lost.send(LinkLost(err));
+            return;
+        }
+    }
+}
+
+// Без связи партия продолжается вдвоём за этим экраном, у зрителя остаётся последняя позиция
+fn lose_link(
+    mut commands: Commands,
+    mut lost: EventReader<LinkLost>,
+    mut opponent: ResMut<Opponent>,
+    mut spectators: ResMut<Spectators>,
+    mut notices: EventWriter<Notice>,
+    locale: Res<Locale>,
+) {
+    if let Some(LinkLost(err)) = lost.read().next() {
+        notices.send(Notice(
+            locale.format("net.lost", &[("error", &locale.error(err))]),
+        ));
+    }
+    lost.clear();
+    commands.remove_resource::<Link>();
+    opponent.side = None;
+    spectators.0 = 0;
+}
+
+impl Connection {
+    fn poll(&mut self) -> Result<Option<SessionEvent>, NetError> {
+        match self {
+            Connection::Player(session) => session.poll(),
+            Connection::Spectator(spectator) => spectator.poll(),
+        }
+    }
+
+    fn game(&self) -> &GameState {
+        match self {
+            Connection::Player(session) => session.game(),
+            Connection::Spectator(spectator) => spectator.game(),
+        }
+    }
+
+    /// The game as both ends know it
+    fn record(&self) -> GameRecord {
+        match self {
+            Connection::Player(session) => {
+                GameRecord::new(session.settings(), session.history(), session.game())
+            }
+            Connection::Spectator(spectator) => {
+                GameRecord::new(spectator.settings(), spectator.history(), spectator.game())
+            }
+        }
+    }
+}
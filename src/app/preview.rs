@@ -0,0 +1,100 @@
+//! Translucent piece over the hovered column.
+
+use bevy::prelude::*;
+
+use super::{
+    animation::pieces_settled, cell_position, AppState, Column, Grid, Opponent, PieceSprites,
+    ELEMENT_SIZE,
+};
+
+pub(super) struct PreviewPlugin;
+
+#[derive(Component)]
+struct Ghost;
+
+const GHOST: Color = Color::rgba(1., 1., 1., 0.5);
+/// Tint of the ghost over a full column
+const BLOCKED: Color = Color::rgba(1., 0.2, 0.2, 0.5);
+
+impl Plugin for PreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_ghost).add_systems(
+            Update,
+            (
+                hide_ghost,
+                show_ghost
+                    .run_if(in_state(AppState::Playing))
+                    .run_if(pieces_settled),
+            )
+                .chain()
+                .after(super::check_mouse_pos),
+        );
+    }
+}
+
+fn spawn_ghost(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2 {
+                    x: ELEMENT_SIZE,
+                    y: ELEMENT_SIZE,
+                }),
+                color: GHOST,
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Ghost,
+    ));
+}
+
+fn hide_ghost(mut q_ghost: Query<&mut Visibility, With<Ghost>>) {
+    for mut visibility in q_ghost.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+// Фишка текущего игрока над столбцом и кольцо на ячейке, куда она упадёт
+fn show_ghost(
+    column: Res<Column>,
+    opponent: Res<Opponent>,
+    sprites: Res<PieceSprites>,
+    q_grid: Query<&Grid>,
+    mut q_ghost: Query<
+        (
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<Ghost>,
+    >,
+    mut gizmos: Gizmos,
+) {
+    let Ok((mut sprite, mut texture, mut transform, mut visibility)) = q_ghost.get_single_mut()
+    else {
+        return;
+    };
+    let (Some(column), Ok(grid)) = (column.0, q_grid.get_single()) else {
+        return;
+    };
+    // Пока думает компьютер или ходят по сети, бросить нельзя и подсказывать нечего
+    if !opponent.is_local(grid.turn()) {
+        return;
+    }
+    let board = grid.board();
+    let above = cell_position(board, column, board.height());
+    *texture = sprites.get(grid.turn());
+    transform.translation = above.extend(1.);
+    *visibility = Visibility::Visible;
+    if board.can_play(column) {
+        sprite.color = GHOST;
+        let row = board.bits().column_height(column);
+        let landing = cell_position(board, column, row);
+        gizmos.circle_2d(landing, ELEMENT_SIZE * 0.4, GHOST);
+    } else {
+        sprite.color = BLOCKED;
+    }
+}
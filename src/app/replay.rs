@@ -0,0 +1,511 @@
+//! Stepping through a recorded game in the window.
+
+use std::time::Duration;
+
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+use connect_four::{notation::GameRecord, GameState, Outcome};
+
+use super::{
+    grid_size, AiTask, AppState, BoardChanged, GameSettings, Grid, History, Locale, Notice, YELLOW,
+};
+
+/// Replay mode, `speed` is how many moves per second autoplay shows
+pub(super) struct ReplayPlugin {
+    pub speed: f32,
+}
+
+/// The game being watched and how many of its moves are on the board
+#[derive(Resource)]
+pub(super) struct Replay {
+    record: GameRecord,
+    position: usize,
+    playing: bool,
+}
+
+impl Replay {
+    pub fn new(record: GameRecord) -> Replay {
+        Replay {
+            record,
+            position: 0,
+            playing: false,
+        }
+    }
+}
+
+/// Speed in moves per second and the time until the next move while the
+/// replay plays by itself
+#[derive(Resource)]
+struct ReplayTimer {
+    speed: f32,
+    timer: Timer,
+}
+
+#[derive(Event, Clone, Copy, PartialEq)]
+enum ReplayCommand {
+    First,
+    Back,
+    Toggle,
+    Forward,
+    Last,
+    /// Show the position after this many moves
+    Jump(usize),
+    Faster,
+    Slower,
+}
+
+#[derive(Component)]
+struct ReplayPanel;
+
+#[derive(Component, Clone, Copy)]
+struct ReplayButton(ReplayCommand);
+
+/// Button in the move list for the position after this many moves
+#[derive(Component)]
+struct MoveButton(usize);
+
+/// The moves of the record, shifted up by `offset` pixels when they do not
+/// fit under the buttons
+#[derive(Component, Default)]
+struct MoveList {
+    offset: f32,
+}
+
+#[derive(Component)]
+struct ReplayStatus;
+
+#[derive(Component)]
+struct ToggleLabel;
+
+/// Width of the panel at the right edge, the board is fitted into the rest
+pub(super) const PANEL_WIDTH: f32 = 240.;
+
+/// Pixels the move list scrolls for one line of the mouse wheel
+const WHEEL_LINE: f32 = 24.;
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 16.;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let speed = self.speed.clamp(MIN_SPEED, MAX_SPEED);
+        app.insert_resource(ReplayTimer {
+            speed,
+            timer: Timer::from_seconds(1. / speed, TimerMode::Repeating),
+        })
+        .add_event::<ReplayCommand>()
+        .add_systems(
+            OnEnter(AppState::Replay),
+            (start_replay, spawn_panel).chain(),
+        )
+        .add_systems(OnExit(AppState::Replay), leave_replay)
+        .add_systems(
+            Update,
+            (
+                replay_keys,
+                press_replay_button,
+                autoplay,
+                apply_replay_commands,
+                (show_position, show_status, follow_position)
+                    .run_if(resource_exists_and_changed::<Replay>),
+                scroll_moves.run_if(on_event::<MouseWheel>()),
+            )
+                .chain()
+                .run_if(in_state(AppState::Replay))
+                .before(super::draw),
+        );
+    }
+}
+
+// Проверяем запись и готовим поле под её размер
+fn start_replay(
+    mut commands: Commands,
+    replay: Res<Replay>,
+    mut q_grid: Query<(&mut Grid, &mut Sprite)>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    mut next_state: ResMut<NextState<AppState>>,
+    locale: Res<Locale>,
+    mut notices: EventWriter<Notice>,
+) {
+    if let Err(err) = replay.record.replay() {
+        notices.send(Notice(
+            locale.format("record.replay_failed", &[("error", &locale.error(&err))]),
+        ));
+        next_state.set(AppState::Menu);
+        return;
+    }
+    let settings = &replay.record.settings;
+    if let Ok((mut grid, mut sprite)) = q_grid.get_single_mut() {
+        grid.0 = GameState::new(settings.new_board());
+        sprite.custom_size = Some(grid_size(settings.width, settings.height));
+    }
+    history.clear();
+    commands.insert_resource(GameSettings(settings.clone()));
+    commands.remove_resource::<AiTask>();
+    changes.send(BoardChanged);
+}
+
+fn spawn_panel(mut commands: Commands, replay: Res<Replay>) {
+    let text_style = |font_size: f32| TextStyle {
+        font_size,
+        color: Color::WHITE,
+        ..default()
+    };
+    let button = |width: Val| ButtonBundle {
+        style: Style {
+            width,
+            padding: UiRect::all(Val::Px(4.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(16.),
+                    right: Val::Px(16.),
+                    bottom: Val::Px(16.),
+                    width: Val::Px(PANEL_WIDTH),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(8.),
+                    overflow: Overflow::clip_y(),
+                    ..default()
+                },
+                ..default()
+            },
+            ReplayPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", text_style(18.)), ReplayStatus));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (command, label) in [
+                        (ReplayCommand::First, "«"),
+                        (ReplayCommand::Back, "‹"),
+                        (ReplayCommand::Toggle, "▶"),
+                        (ReplayCommand::Forward, "›"),
+                        (ReplayCommand::Last, "»"),
+                        (ReplayCommand::Slower, "−"),
+                        (ReplayCommand::Faster, "+"),
+                    ] {
+                        parent
+                            .spawn((button(Val::Px(30.)), ReplayButton(command)))
+                            .with_children(|parent| {
+                                let mut label =
+                                    parent.spawn(TextBundle::from_section(label, text_style(20.)));
+                                if command == ReplayCommand::Toggle {
+                                    label.insert(ToggleLabel);
+                                }
+                            });
+                    }
+                });
+            // Список ходов прокручивается колесом мыши внутри оставшегося места
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_grow: 1.,
+                        min_height: Val::Px(0.),
+                        overflow: Overflow::clip_y(),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    row_gap: Val::Px(8.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            MoveList::default(),
+                        ))
+                        .with_children(|parent| {
+                            // Ходы парами, как в записи: номер, ход красных, ход синих
+                            for (row, pair) in replay.record.moves.chunks(2).enumerate() {
+                                parent
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            column_gap: Val::Px(4.),
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent.spawn(
+                                            TextBundle::from_section(
+                                                format!("{}.", row + 1),
+                                                text_style(16.),
+                                            )
+                                            .with_style(Style {
+                                                width: Val::Px(40.),
+                                                ..default()
+                                            }),
+                                        );
+                                        for (i, column) in pair.iter().enumerate() {
+                                            let played = row * 2 + i + 1;
+                                            parent
+                                                .spawn((
+                                                    button(Val::Px(60.)),
+                                                    ReplayButton(ReplayCommand::Jump(played)),
+                                                    MoveButton(played),
+                                                ))
+                                                .with_children(|parent| {
+                                                    parent.spawn(TextBundle::from_section(
+                                                        (column + 1).to_string(),
+                                                        text_style(16.),
+                                                    ));
+                                                });
+                                        }
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn leave_replay(
+    mut commands: Commands,
+    q_panel: Query<Entity, With<ReplayPanel>>,
+    mut q_grid: Query<&mut Grid>,
+    mut history: ResMut<History>,
+    mut changes: EventWriter<BoardChanged>,
+    settings: Res<GameSettings>,
+) {
+    for entity in q_panel.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Replay>();
+    if let Ok(mut grid) = q_grid.get_single_mut() {
+        grid.0 = GameState::new(settings.new_board());
+    }
+    history.clear();
+    changes.send(BoardChanged);
+}
+
+// Стрелки — ход назад и вперёд, Home/End — начало и конец, пробел — автопросмотр,
+// +/- — скорость, Escape — выход в меню
+fn replay_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut commands: EventWriter<ReplayCommand>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (key, command) in [
+        (KeyCode::Home, ReplayCommand::First),
+        (KeyCode::ArrowLeft, ReplayCommand::Back),
+        (KeyCode::Space, ReplayCommand::Toggle),
+        (KeyCode::ArrowRight, ReplayCommand::Forward),
+        (KeyCode::End, ReplayCommand::Last),
+        (KeyCode::Minus, ReplayCommand::Slower),
+        (KeyCode::NumpadSubtract, ReplayCommand::Slower),
+        (KeyCode::Equal, ReplayCommand::Faster),
+        (KeyCode::NumpadAdd, ReplayCommand::Faster),
+    ] {
+        if keys.just_pressed(key) {
+            commands.send(command);
+        }
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+    }
+}
+
+fn press_replay_button(
+    q_buttons: Query<(&Interaction, &ReplayButton), Changed<Interaction>>,
+    mut commands: EventWriter<ReplayCommand>,
+) {
+    for (interaction, button) in q_buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            commands.send(button.0);
+        }
+    }
+}
+
+fn autoplay(time: Res<Time>, mut timer: ResMut<ReplayTimer>, mut replay: ResMut<Replay>) {
+    if !replay.playing || !timer.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    if replay.position < replay.record.moves.len() {
+        replay.position += 1;
+    }
+    if replay.position == replay.record.moves.len() {
+        replay.playing = false;
+    }
+}
+
+// Ручной переход к ходу останавливает автопросмотр
+fn apply_replay_commands(
+    mut commands: EventReader<ReplayCommand>,
+    mut replay: ResMut<Replay>,
+    mut timer: ResMut<ReplayTimer>,
+) {
+    let last = replay.record.moves.len();
+    for command in commands.read() {
+        let position = match *command {
+            ReplayCommand::First => 0,
+            ReplayCommand::Back => replay.position.saturating_sub(1),
+            ReplayCommand::Forward => (replay.position + 1).min(last),
+            ReplayCommand::Last => last,
+            ReplayCommand::Jump(played) => played.min(last),
+            ReplayCommand::Toggle => {
+                replay.playing = !replay.playing;
+                if replay.playing && replay.position == last {
+                    replay.position = 0;
+                }
+                timer.timer.reset();
+                continue;
+            }
+            ReplayCommand::Faster | ReplayCommand::Slower => {
+                let speed = if *command == ReplayCommand::Faster {
+                    timer.speed * 2.
+                } else {
+                    timer.speed / 2.
+                };
+                timer.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+                let duration = Duration::from_secs_f32(1. / timer.speed);
+                timer.timer.set_duration(duration);
+                // Чтобы в статусе сразу появилась новая скорость
+                replay.set_changed();
+                continue;
+            }
+        };
+        replay.position = position;
+        replay.playing = false;
+    }
+}
+
+// Восстанавливаем поле по первым ходам записи, фишки расставит обычный `draw`
+fn show_position(
+    replay: Res<Replay>,
+    mut q_grid: Query<&mut Grid>,
+    mut changes: EventWriter<BoardChanged>,
+    mut q_toggle: Query<&mut Text, With<ToggleLabel>>,
+    mut q_moves: Query<(&MoveButton, &mut BackgroundColor)>,
+    locale: Res<Locale>,
+    mut notices: EventWriter<Notice>,
+) {
+    if let Ok(mut grid) = q_grid.get_single_mut() {
+        match replay.record.replay_moves(replay.position) {
+            Ok((game, _)) => {
+                grid.0 = game;
+                changes.send(BoardChanged);
+            }
+            Err(err) => {
+                notices.send(Notice(
+                    locale.format("record.replay_failed", &[("error", &locale.error(&err))]),
+                ));
+            }
+        }
+    }
+    if let Ok(mut text) = q_toggle.get_single_mut() {
+        text.sections[0].value = if replay.playing { "❚❚" } else { "▶" }.to_string();
+    }
+    for (button, mut color) in q_moves.iter_mut() {
+        *color = if button.0 == replay.position {
+            YELLOW.with_a(0.5).into()
+        } else {
+            Color::DARK_GRAY.into()
+        };
+    }
+}
+
+fn show_status(
+    replay: Res<Replay>,
+    timer: Res<ReplayTimer>,
+    mut q_status: Query<&mut Text, With<ReplayStatus>>,
+    locale: Res<Locale>,
+) {
+    let Ok(mut text) = q_status.get_single_mut() else {
+        return;
+    };
+    let record = &replay.record;
+    let total = record.moves.len();
+    let mut lines = vec![
+        format!("{} — {}", record.red, record.blue),
+        record.date.clone(),
+        locale.format(
+            "replay.position",
+            &[("position", &replay.position), ("total", &total)],
+        ),
+        locale.format("replay.speed", &[("speed", &timer.speed)]),
+    ];
+    if replay.position == total {
+        lines.push(match record.result {
+            Some(Outcome::Win(player)) => {
+                locale.format("result.win", &[("player", &locale.player(player))])
+            }
+            Some(Outcome::Draw) => locale.get("result.draw").to_string(),
+            None => locale.get("result.unfinished").to_string(),
+        });
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+fn scroll_moves(
+    mut wheel: EventReader<MouseWheel>,
+    mut q_list: Query<(&mut MoveList, &mut Style, &Node, &Parent)>,
+    q_nodes: Query<&Node>,
+) {
+    let Ok((mut list, mut style, node, parent)) = q_list.get_single_mut() else {
+        return;
+    };
+    let Ok(view) = q_nodes.get(parent.get()) else {
+        return;
+    };
+    let mut offset = list.offset;
+    for event in wheel.read() {
+        offset -= match event.unit {
+            MouseScrollUnit::Line => event.y * WHEEL_LINE,
+            MouseScrollUnit::Pixel => event.y,
+        };
+    }
+    scroll_to(&mut list, &mut style, offset, node.size().y - view.size().y);
+}
+
+// Строка текущего хода остаётся видна при просмотре и переходах по кнопкам
+fn follow_position(
+    replay: Res<Replay>,
+    mut q_list: Query<(&mut MoveList, &mut Style, &Node, &Parent)>,
+    q_nodes: Query<&Node>,
+) {
+    let Ok((mut list, mut style, node, parent)) = q_list.get_single_mut() else {
+        return;
+    };
+    let Ok(view) = q_nodes.get(parent.get()) else {
+        return;
+    };
+    let rows = replay.record.moves.len().div_ceil(2);
+    if rows == 0 {
+        return;
+    }
+    // Высота строки вместе с промежутком до следующей
+    let row_height = node.size().y / rows as f32;
+    let top = (replay.position.saturating_sub(1) / 2) as f32 * row_height;
+    let view_height = view.size().y;
+    let offset = list.offset.min(top).max(top + row_height - view_height);
+    scroll_to(&mut list, &mut style, offset, node.size().y - view_height);
+}
+
+/// Shifts the move list, no further than its end allows
+fn scroll_to(list: &mut MoveList, style: &mut Style, offset: f32, max_offset: f32) {
+    list.offset = offset.min(max_offset).max(0.);
+    style.top = Val::Px(-list.offset);
+}
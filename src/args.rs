@@ -0,0 +1,78 @@
+//! Command line parsing shared by the game and the dedicated server.
+
+/// Flags with their values, given as `--width 9` or `--width=9`
+pub struct Args<I> {
+    args: I,
+    /// The last flag
+    flag: String,
+    /// Value given after `=` with the last flag
+    inline: Option<String>,
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    pub fn new(args: impl IntoIterator<IntoIter = I>) -> Args<I> {
+        Args {
+            args: args.into_iter(),
+            flag: String::new(),
+            inline: None,
+        }
+    }
+
+    /// The next flag, a flag that takes no value ignores one given after `=`
+    pub fn next_flag(&mut self) -> Option<String> {
+        let arg = self.args.next()?;
+        (self.flag, self.inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        Some(self.flag.clone())
+    }
+
+    /// The value of the last flag, after `=` or in the next argument
+    pub fn value(&mut self) -> Result<String, String> {
+        self.inline
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or_else(|| format!("{} needs a value", self.flag))
+    }
+
+    /// The value of the last flag as a number
+    pub fn number(&mut self) -> Result<u32, String> {
+        let value = self.value()?;
+        value
+            .parse()
+            .map_err(|_| format!("{} expects a number, got `{}`", self.flag, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Args<std::vec::IntoIter<String>> {
+        Args::new(args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn values_follow_the_flag_or_an_equals_sign() {
+        let mut args = args(&["--width=9", "--height", "7", "--headless=yes", "--join"]);
+        assert_eq!(args.next_flag().as_deref(), Some("--width"));
+        assert_eq!(args.number(), Ok(9));
+        assert_eq!(args.next_flag().as_deref(), Some("--height"));
+        assert_eq!(args.number(), Ok(7));
+        assert_eq!(args.next_flag().as_deref(), Some("--headless"));
+        assert_eq!(args.next_flag().as_deref(), Some("--join"));
+        assert_eq!(args.value(), Err("--join needs a value".to_string()));
+        assert_eq!(args.next_flag(), None);
+    }
+
+    #[test]
+    fn numbers_are_checked() {
+        let mut args = args(&["--depth", "deep"]);
+        args.next_flag();
+        assert_eq!(
+            args.number(),
+            Err("--depth expects a number, got `deep`".to_string())
+        );
+    }
+}
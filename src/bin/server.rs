@@ -0,0 +1,99 @@
+//! Headless server for network games, players connect with `--join`.
+//! With `--ws-addr` it also hosts games of WebSocket clients.
+//! Spectators are not supported, `--watch` is refused.
+
+use std::process::ExitCode;
+use std::thread;
+
+use connect_four::{args::Args, server::Server, ws::WsServer, Settings};
+
+const USAGE: &str = "\
+Usage: server [OPTIONS]
+
+Pairs players who connect with `connect-four --join <ADDR>`. Games cannot
+be watched, clients connecting with `--watch` are refused.
+
+Options:
+  --addr <ADDR>        address to listen at, 0.0.0.0:7654 by default
+  --ws-addr <ADDR>     also accept WebSocket clients at the address
+  --width <N>          number of columns
+  --height <N>         number of rows
+  --win-length <N>     pieces in a row needed to win
+  -h, --help           print this help";
+
+const DEFAULT_ADDR: &str = "0.0.0.0:7654";
+
+/// What the command line asks for
+struct Options {
+    addr: String,
+    ws_addr: Option<String>,
+    settings: Settings,
+}
+
+fn main() -> ExitCode {
+    let options = match parse(std::env::args().skip(1)) {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let Options {
+        addr,
+        ws_addr,
+        settings,
+    } = options;
+    let server = match Server::bind(&addr, &settings) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("{}: {}", addr, err);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "Hosting {}x{} games with {} in a row at {}",
+        settings.width, settings.height, settings.win_length, addr
+    );
+    if let Some(ws_addr) = ws_addr {
+        let ws_server = match WsServer::bind(&ws_addr, &settings) {
+            Ok(ws_server) => ws_server,
+            Err(err) => {
+                eprintln!("{}: {}", ws_addr, err);
+                return ExitCode::FAILURE;
+            }
+        };
+        println!("WebSocket clients connect to ws://{}", ws_addr);
+        thread::spawn(move || ws_server.run());
+    }
+    server.run();
+    ExitCode::SUCCESS
+}
+
+/// `None` when help is asked for
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut ws_addr = None;
+    let mut settings = Settings::default();
+    let mut args = Args::new(args);
+    while let Some(flag) = args.next_flag() {
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--addr" => addr = args.value()?,
+            "--ws-addr" => ws_addr = Some(args.value()?),
+            "--width" => settings.width = args.number()?,
+            "--height" => settings.height = args.number()?,
+            "--win-length" => settings.win_length = args.number()?,
+            _ => return Err(format!("unknown option `{}`", flag)),
+        }
+    }
+    settings.validate().map_err(|err| err.to_string())?;
+    Ok(Some(Options {
+        addr,
+        ws_addr,
+        settings,
+    }))
+}
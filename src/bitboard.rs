@@ -0,0 +1,301 @@
+use crate::board::ElementType;
+
+/// One bit per cell, wide enough for boards up to 9x7 and a bit more
+pub type Mask = u128;
+
+/// Length of a winning line on the standard board
+pub const DEFAULT_WIN_LENGTH: u32 = 4;
+
+/// Longest winning line a `BitBoard` supports
+pub const MAX_WIN_LENGTH: u32 = 16;
+
+/// Board stored as two bit masks, one per player, for fast search.
+///
+/// Column `x` takes `height + 1` bits starting at bit `x * (height + 1)`, row 0 is
+/// the lowest bit. The extra bit on top of every column always stays empty, so
+/// a line shifted past the edge of the board never matches.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitBoard {
+    width: u32,
+    height: u32,
+    win_length: u32,
+    masks: [Mask; 2],
+    /// The lowest cell of every column
+    bottom: Mask,
+    /// Number of pieces in every column
+    heights: Vec<u32>,
+    moves: u32,
+}
+
+impl BitBoard {
+    /// Creates an empty board with the usual four in a row to win
+    pub fn new(width: u32, height: u32) -> BitBoard {
+        BitBoard::with_win_length(width, height, DEFAULT_WIN_LENGTH)
+    }
+
+    /// Creates an empty board, panics if it does not fit into the masks
+    /// or the line is longer than `MAX_WIN_LENGTH`
+    pub fn with_win_length(width: u32, height: u32, win_length: u32) -> BitBoard {
+        assert!(
+            BitBoard::fits(width, height),
+            "a {}x{} board does not fit into a bitboard",
+            width,
+            height
+        );
+        assert!(
+            (1..=MAX_WIN_LENGTH).contains(&win_length),
+            "a line of {} is not supported",
+            win_length
+        );
+        let column_bits = height + 1;
+        BitBoard {
+            width,
+            height,
+            win_length,
+            masks: [0; 2],
+            bottom: (0..width).fold(0, |bottom, x| bottom | 1 << (x * column_bits)),
+            heights: vec![0; width as usize],
+            moves: 0,
+        }
+    }
+
+    /// Whether a board of this size can be stored in a bitboard
+    pub fn fits(width: u32, height: u32) -> bool {
+        // In u64 the product cannot overflow for any u32 sizes
+        width > 0 && height > 0 && width as u64 * (height as u64 + 1) <= Mask::BITS as u64
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of pieces in a row needed to win
+    pub fn win_length(&self) -> u32 {
+        self.win_length
+    }
+
+    /// Number of pieces on the board
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.moves == self.width * self.height
+    }
+
+    fn bit(&self, x: u32, y: u32) -> Mask {
+        1 << (x * (self.height + 1) + y)
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<ElementType> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let bit = self.bit(x, y);
+        if self.masks[0] & bit != 0 {
+            Some(ElementType::Red)
+        } else if self.masks[1] & bit != 0 {
+            Some(ElementType::Blue)
+        } else {
+            None
+        }
+    }
+
+    /// Number of pieces in the column
+    pub fn column_height(&self, column: u32) -> u32 {
+        self.heights[column as usize]
+    }
+
+    pub fn can_play(&self, column: u32) -> bool {
+        column < self.width && self.heights[column as usize] < self.height
+    }
+
+    /// Drops a piece into the column and returns its row.
+    /// The caller checks `can_play` first.
+    pub fn play(&mut self, column: u32, player: ElementType) -> u32 {
+        let row = self.heights[column as usize];
+        self.masks[player as usize] |= self.bit(column, row);
+        self.heights[column as usize] += 1;
+        self.moves += 1;
+        row
+    }
+
+    /// Takes the top piece out of the column and returns its owner and row
+    pub fn undo(&mut self, column: u32) -> Option<(ElementType, u32)> {
+        let row = self.heights.get(column as usize)?.checked_sub(1)?;
+        let player = self.get(column, row)?;
+        self.masks[player as usize] &= !self.bit(column, row);
+        self.heights[column as usize] = row;
+        self.moves -= 1;
+        Some((player, row))
+    }
+
+    pub fn clear(&mut self) {
+        self.masks = [0; 2];
+        self.heights.iter_mut().for_each(|height| *height = 0);
+        self.moves = 0;
+    }
+
+    /// Whether the player has a winning line anywhere on the board
+    pub fn is_win(&self, player: ElementType) -> bool {
+        let mask = self.masks[player as usize];
+        self.shifts().into_iter().any(|shift| {
+            // Every bit left is the start of a line of `win_length` pieces
+            (1..self.win_length).fold(mask, |line, i| {
+                line & mask.checked_shr(i * shift).unwrap_or(0)
+            }) != 0
+        })
+    }
+
+    /// Distance in bits between neighbouring cells of a vertical,
+    /// horizontal and both diagonal lines
+    fn shifts(&self) -> [u32; 4] {
+        [1, self.height + 1, self.height + 2, self.height]
+    }
+
+    /// Pieces of the player as a bit mask
+    pub fn mask(&self, player: ElementType) -> Mask {
+        self.masks[player as usize]
+    }
+
+    /// Every occupied cell as a bit mask
+    pub fn occupied(&self) -> Mask {
+        self.masks[0] | self.masks[1]
+    }
+
+    /// The lowest cell of every column
+    pub fn bottom_mask(&self) -> Mask {
+        self.bottom
+    }
+
+    /// Every cell of the board, without the empty bit on top of the columns
+    pub fn board_mask(&self) -> Mask {
+        self.bottom * ((1 << self.height) - 1)
+    }
+
+    /// Every cell of the column
+    pub fn column_mask(&self, column: u32) -> Mask {
+        ((1 << self.height) - 1) << (column * (self.height + 1))
+    }
+
+    /// Empty cells that would complete a winning line for the pieces in `position`.
+    /// `occupied` holds the pieces of both players.
+    pub fn winning_cells(&self, position: Mask, occupied: Mask) -> Mask {
+        let length = self.win_length as usize;
+        let mut cells = 0;
+        for shift in self.shifts() {
+            // `above[i]` has a bit where the next `i` cells along the line are taken,
+            // `below[i]` where the previous `i` cells are
+            let mut above = [!0; MAX_WIN_LENGTH as usize];
+            let mut below = [!0; MAX_WIN_LENGTH as usize];
+            for i in 1..length {
+                let distance = i as u32 * shift;
+                above[i] = above[i - 1] & position.checked_shr(distance).unwrap_or(0);
+                below[i] = below[i - 1] & position.checked_shl(distance).unwrap_or(0);
+            }
+            // The empty cell can be at any place of the line
+            for gap in 0..length {
+                cells |= above[length - 1 - gap] & below[gap];
+            }
+        }
+        cells & (self.board_mask() ^ occupied)
+    }
+
+    /// Unique key of the position, suitable for hashing
+    pub fn key(&self) -> Mask {
+        self.masks[0] + self.occupied() + self.bottom_mask()
+    }
+}
+
+/// Columns from the centre outwards, the order both searches try moves in.
+/// Central moves are usually stronger, so they cut the search sooner.
+pub(crate) fn centre_out(width: u32) -> impl Iterator<Item = u32> {
+    let centre = (width - 1) / 2;
+    // On an even board the extra column is on the right, so the right goes first
+    (0..width).map(move |i| {
+        if i % 2 == 1 {
+            centre + (i + 1) / 2
+        } else {
+            centre - i / 2
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_tried_from_the_centre_outwards() {
+        assert_eq!(centre_out(7).collect::<Vec<_>>(), [3, 4, 2, 5, 1, 6, 0]);
+        assert_eq!(centre_out(6).collect::<Vec<_>>(), [2, 3, 1, 4, 0, 5]);
+        assert_eq!(centre_out(1).collect::<Vec<_>>(), [0]);
+    }
+
+    /// Xorshift, enough to pick random columns without a dependency
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as u32
+        }
+    }
+
+    /// Looks for a line cell by cell, the way `Board::get_matches` walks the board
+    fn has_line(board: &BitBoard, player: ElementType) -> bool {
+        let length = board.win_length() as i64;
+        let owned =
+            |x: i64, y: i64| x >= 0 && y >= 0 && board.get(x as u32, y as u32) == Some(player);
+        (0..board.width() as i64).any(|x| {
+            (0..board.height() as i64).any(|y| {
+                [(1, 0), (0, 1), (1, 1), (1, -1)]
+                    .into_iter()
+                    .any(|(dx, dy)| (0..length).all(|i| owned(x + dx * i, y + dy * i)))
+            })
+        })
+    }
+
+    #[test]
+    fn wins_agree_with_a_search_cell_by_cell() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        let sizes = [
+            (7, 6, 4),
+            (9, 7, 5),
+            (4, 4, 3),
+            (16, 7, 4),
+            (10, 1, 3),
+            (1, 12, 4),
+        ];
+        for (width, height, win_length) in sizes {
+            for _ in 0..50 {
+                let mut board = BitBoard::with_win_length(width, height, win_length);
+                let mut player = ElementType::Red;
+                // Play on past the first line, full boards have many of them
+                while !board.is_full() {
+                    let column = random.below(width);
+                    if !board.can_play(column) {
+                        continue;
+                    }
+                    board.play(column, player);
+                    for side in [ElementType::Red, ElementType::Blue] {
+                        assert_eq!(
+                            board.is_win(side),
+                            has_line(&board, side),
+                            "{:?} on {:?}",
+                            side,
+                            board
+                        );
+                    }
+                    player = player.other();
+                }
+            }
+        }
+    }
+}
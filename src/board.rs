@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::bitboard::BitBoard;
+
+/// Owner of a piece on the board
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ElementType {
+    Red = 0,
+    Blue = 1,
+}
+
+impl ElementType {
+    /// The player who moves after this one
+    pub fn other(self) -> ElementType {
+        match self {
+            ElementType::Red => ElementType::Blue,
+            ElementType::Blue => ElementType::Red,
+        }
+    }
+
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Cell coordinates, `x` is the column and `y` is the row counted from the bottom
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pos {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Pos {
+    pub fn new(x: u32, y: u32) -> Pos {
+        Pos { x, y }
+    }
+}
+
+impl From<[u32; 2]> for Pos {
+    fn from([x, y]: [u32; 2]) -> Pos {
+        Pos { x, y }
+    }
+}
+
+/// The grid of cells, stored as a `BitBoard`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Board {
+    bits: BitBoard,
+}
+
+enum MatchDirection {
+    Horizontal,
+    Vertical,
+    /// From the bottom left to the top right
+    DiagonalUp,
+    /// From the top left to the bottom right
+    DiagonalDown,
+}
+
+impl MatchDirection {
+    /// Offset between two neighbouring cells of a line in this direction
+    fn delta(&self) -> (i32, i32) {
+        match self {
+            MatchDirection::Horizontal => (1, 0),
+            MatchDirection::Vertical => (0, 1),
+            MatchDirection::DiagonalUp => (1, 1),
+            MatchDirection::DiagonalDown => (1, -1),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Match {
+    /// A straight line of at least the winning length in any direction
+    Straight(HashSet<Pos>),
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct Matches {
+    matches: Vec<Match>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElemError {
+    /// The cell is empty
+    NoElem,
+    /// The column is outside of the board
+    OutOfBounds,
+    /// Every cell of the column is already taken
+    ColumnFull,
+    /// Somebody has already won or the game is drawn
+    GameAlreadyOver,
+}
+
+impl fmt::Display for ElemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElemError::NoElem => write!(f, "the cell is empty"),
+            ElemError::OutOfBounds => write!(f, "the column is outside of the board"),
+            ElemError::ColumnFull => write!(f, "the column is full"),
+            ElemError::GameAlreadyOver => write!(f, "the game is already over"),
+        }
+    }
+}
+
+impl std::error::Error for ElemError {}
+
+impl Match {
+    /// The cells at both ends of the line
+    pub fn ends(&self) -> Option<(Pos, Pos)> {
+        match self {
+            Match::Straight(cells) => Some((*cells.iter().min()?, *cells.iter().max()?)),
+        }
+    }
+}
+
+impl Matches {
+    fn add(&mut self, mat: Match) {
+        self.matches.push(mat)
+    }
+
+    /// Adds the line as a match if it is long enough to win
+    fn add_line(&mut self, line: &[Pos], win_length: u32) {
+        if line.len() >= win_length as usize {
+            self.add(Match::Straight(line.iter().cloned().collect()))
+        }
+    }
+
+    fn append(&mut self, other: &mut Matches) {
+        self.matches.append(&mut other.matches);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Match> {
+        self.matches.iter()
+    }
+
+    /// Returns the coordinates of all matches in this collection without any repeated values
+    pub fn without_duplicates(&self) -> HashSet<Pos> {
+        self.matches
+            .iter()
+            .flat_map(|mat| match mat {
+                Match::Straight(mat) => mat,
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+}
+
+impl Default for Board {
+    /// The standard 7x6 board
+    fn default() -> Board {
+        Board::new(7, 6)
+    }
+}
+
+impl Board {
+    /// Creates an empty board where four in a row wins,
+    /// panics if it does not fit into a `BitBoard`
+    pub fn new(width: u32, height: u32) -> Board {
+        Board {
+            bits: BitBoard::new(width, height),
+        }
+    }
+
+    /// Creates an empty board where `win_length` pieces in a row win
+    pub fn with_win_length(width: u32, height: u32, win_length: u32) -> Board {
+        Board {
+            bits: BitBoard::with_win_length(width, height, win_length),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.bits.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.bits.height()
+    }
+
+    /// Number of pieces in a row needed to win
+    pub fn win_length(&self) -> u32 {
+        self.bits.win_length()
+    }
+
+    /// The underlying bitboard, for searches that need fast play and undo
+    pub fn bits(&self) -> &BitBoard {
+        &self.bits
+    }
+
+    pub fn get(&self, pos: &Pos) -> Result<ElementType, ElemError> {
+        self.bits.get(pos.x, pos.y).ok_or(ElemError::NoElem)
+    }
+
+    /// Number of pieces on the board
+    pub fn len(&self) -> usize {
+        self.bits.moves() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.moves() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.bits.is_full()
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear();
+    }
+
+    /// Drops a piece into the column and returns the row where it landed
+    pub fn add_at_column(
+        &mut self,
+        column: u32,
+        element_type: ElementType,
+    ) -> Result<u32, ElemError> {
+        if column >= self.width() {
+            return Err(ElemError::OutOfBounds);
+        }
+        if !self.bits.can_play(column) {
+            return Err(ElemError::ColumnFull);
+        }
+        Ok(self.bits.play(column, element_type))
+    }
+
+    /// Takes the top piece out of the column and returns its owner and row
+    pub fn remove_top(&mut self, column: u32) -> Result<(ElementType, u32), ElemError> {
+        if column >= self.width() {
+            return Err(ElemError::OutOfBounds);
+        }
+        self.bits.undo(column).ok_or(ElemError::NoElem)
+    }
+
+    /// Whether another piece can be dropped into the column
+    pub fn can_play(&self, column: u32) -> bool {
+        self.bits.can_play(column)
+    }
+
+    pub fn get_matches(&self) -> Matches {
+        if !self.bits.is_win(ElementType::Red) && !self.bits.is_win(ElementType::Blue) {
+            return Matches::default();
+        }
+        let mut matches = self.straight_matches(MatchDirection::Horizontal);
+        matches.append(&mut self.straight_matches(MatchDirection::Vertical));
+        matches.append(&mut self.straight_matches(MatchDirection::DiagonalUp));
+        matches.append(&mut self.straight_matches(MatchDirection::DiagonalDown));
+        matches
+    }
+
+    fn straight_matches(&self, direction: MatchDirection) -> Matches {
+        let mut matches = Matches::default();
+        for start in self.line_starts(&direction) {
+            let mut current_match = vec![];
+            let mut previous_type = None;
+            let mut pos = Some(start);
+            while let Some(current) = pos {
+                match self.get(&current) {
+                    Ok(current_type) if previous_type == Some(current_type) => {
+                        current_match.push(current);
+                    }
+                    Ok(current_type) => {
+                        matches.add_line(&current_match, self.win_length());
+                        current_match = vec![current];
+                        previous_type = Some(current_type);
+                    }
+                    // An empty cell breaks the line
+                    Err(_) => {
+                        matches.add_line(&current_match, self.win_length());
+                        current_match = vec![];
+                        previous_type = None;
+                    }
+                }
+                pos = self.step(current, direction.delta());
+            }
+            matches.add_line(&current_match, self.win_length());
+        }
+        matches
+    }
+
+    /// Returns the first cell of every line that runs in the given direction
+    fn line_starts(&self, direction: &MatchDirection) -> Vec<Pos> {
+        let left_column = (0..self.height()).map(|y| Pos::new(0, y));
+        match direction {
+            MatchDirection::Horizontal => left_column.collect(),
+            MatchDirection::Vertical => (0..self.width()).map(|x| Pos::new(x, 0)).collect(),
+            MatchDirection::DiagonalUp => left_column
+                .chain((1..self.width()).map(|x| Pos::new(x, 0)))
+                .collect(),
+            MatchDirection::DiagonalDown => left_column
+                .chain((1..self.width()).map(|x| Pos::new(x, self.height() - 1)))
+                .collect(),
+        }
+    }
+
+    fn step(&self, pos: Pos, (dx, dy): (i32, i32)) -> Option<Pos> {
+        let x = pos.x.checked_add_signed(dx)?;
+        let y = pos.y.checked_add_signed(dy)?;
+        if x < self.width() && y < self.height() {
+            Some(Pos::new(x, y))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ElementType::{Blue, Red};
+
+    /// The standard board with the pieces dropped in this order
+    fn build(pieces: &[(u32, ElementType)]) -> Board {
+        let mut board = Board::default();
+        for &(column, owner) in pieces {
+            board.add_at_column(column, owner).unwrap();
+        }
+        board
+    }
+
+    fn cells(positions: &[[u32; 2]]) -> HashSet<Pos> {
+        positions.iter().map(|&pos| Pos::from(pos)).collect()
+    }
+
+    #[test]
+    fn a_line_up_to_the_right_wins() {
+        let board = build(&[
+            (0, Red),
+            (1, Blue),
+            (1, Red),
+            (2, Blue),
+            (2, Blue),
+            (2, Red),
+            (3, Blue),
+            (3, Blue),
+            (3, Blue),
+            (3, Red),
+        ]);
+        let matches = board.get_matches();
+        assert_eq!(
+            matches.without_duplicates(),
+            cells(&[[0, 0], [1, 1], [2, 2], [3, 3]])
+        );
+        let ends = matches.iter().next().and_then(Match::ends);
+        assert_eq!(ends, Some((Pos::new(0, 0), Pos::new(3, 3))));
+    }
+
+    #[test]
+    fn a_line_down_to_the_right_wins() {
+        let board = build(&[
+            (3, Red),
+            (2, Blue),
+            (2, Red),
+            (1, Blue),
+            (1, Blue),
+            (1, Red),
+            (0, Blue),
+            (0, Blue),
+            (0, Blue),
+            (0, Red),
+        ]);
+        let matches = board.get_matches();
+        assert_eq!(
+            matches.without_duplicates(),
+            cells(&[[0, 3], [1, 2], [2, 1], [3, 0]])
+        );
+        let ends = matches.iter().next().and_then(Match::ends);
+        assert_eq!(ends, Some((Pos::new(0, 3), Pos::new(3, 0))));
+    }
+
+    #[test]
+    fn one_piece_can_complete_two_lines() {
+        let mut board = build(&[(3, Red), (3, Red), (3, Red)]);
+        for column in 0..3 {
+            for _ in 0..3 {
+                board.add_at_column(column, Blue).unwrap();
+            }
+            board.add_at_column(column, Red).unwrap();
+        }
+        assert!(board.get_matches().is_empty());
+
+        board.add_at_column(3, Red).unwrap();
+        let matches = board.get_matches();
+        assert_eq!(matches.iter().count(), 2);
+        assert_eq!(
+            matches.without_duplicates(),
+            cells(&[[0, 3], [1, 3], [2, 3], [3, 3], [3, 0], [3, 1], [3, 2]])
+        );
+    }
+
+    #[test]
+    fn moves_off_the_board_or_into_a_full_column_are_refused() {
+        let mut board = Board::default();
+        assert_eq!(board.add_at_column(7, Red), Err(ElemError::OutOfBounds));
+        assert_eq!(board.remove_top(7), Err(ElemError::OutOfBounds));
+        assert_eq!(board.remove_top(0), Err(ElemError::NoElem));
+        for row in 0..6 {
+            assert_eq!(board.add_at_column(0, Red), Ok(row));
+        }
+        assert_eq!(board.add_at_column(0, Blue), Err(ElemError::ColumnFull));
+        assert_eq!(board.len(), 6);
+    }
+}
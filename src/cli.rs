@@ -0,0 +1,301 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use connect_four::{
+    ai::AiConfig,
+    args::Args,
+    i18n::{self, Catalog},
+    notation::GameRecord,
+    ElementType, Settings,
+};
+
+pub const USAGE: &str = "\
+Usage: connect-four [OPTIONS]
+
+Options:
+  --headless           play in the terminal instead of opening a window
+  --width <N>          number of columns
+  --height <N>         number of rows
+  --win-length <N>     pieces in a row needed to win
+  --ai <red|blue>      side played by the computer
+  --depth <N>          how many moves ahead the computer looks
+  --think-time <MS>    longest time the computer thinks about a move, in ms
+  --settings <PATH>    settings file, assets/settings.cfg by default
+  --load <PATH>        continue a saved game, the board comes from the record
+  --replay <PATH>      step through a saved game
+  --replay-speed <N>   moves per second when the replay plays by itself
+  --host <ADDR>        wait for the other player at the address, e.g. 0.0.0.0:7654
+  --join <ADDR>        play against the player waiting at the address
+  --watch <ADDR>       watch the game hosted at the address by a player,
+                       the dedicated server has no spectators
+  -h, --help           print this help";
+
+const SETTINGS_PATH: &str = "assets/settings.cfg";
+
+/// Directory with a `<language>.lang` catalog per language
+const LOCALES_PATH: &str = "assets/locales";
+
+/// What the front-end shows first
+pub enum Start {
+    NewGame,
+    /// Continue a saved game
+    Continue(GameRecord),
+    /// Step through a saved game
+    Replay(GameRecord),
+    /// Play red against a guest connecting to the address
+    Host(String),
+    /// Play blue on the board of the host at the address
+    Join(String),
+    /// Follow the game of the host at the address without playing
+    Watch(String),
+}
+
+impl Start {
+    /// The board and rules of a saved game win over the settings file and
+    /// the size flags
+    pub fn settings(&self, settings: Settings) -> Settings {
+        match self {
+            Start::Continue(record) | Start::Replay(record) => record.settings.clone(),
+            Start::NewGame | Start::Host(_) | Start::Join(_) | Start::Watch(_) => settings,
+        }
+    }
+}
+
+/// Command line arguments, values that are not given come from the settings file
+#[derive(Debug, Default)]
+pub struct Options {
+    pub headless: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub win_length: Option<u32>,
+    pub ai: Option<ElementType>,
+    pub depth: Option<u32>,
+    /// Milliseconds the computer may spend on a move
+    pub think_time: Option<u32>,
+    pub settings: Option<PathBuf>,
+    pub load: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub replay_speed: Option<f32>,
+    pub host: Option<String>,
+    pub join: Option<String>,
+    pub watch: Option<String>,
+    pub help: bool,
+}
+
+impl Options {
+    /// Parses the arguments without the program name.
+    /// Values can be given as `--width 9` or `--width=9`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = Args::new(args);
+        while let Some(flag) = args.next_flag() {
+            match flag.as_str() {
+                "--headless" => options.headless = true,
+                "-h" | "--help" => options.help = true,
+                "--width" => options.width = Some(args.number()?),
+                "--height" => options.height = Some(args.number()?),
+                "--win-length" => options.win_length = Some(args.number()?),
+                "--depth" => options.depth = Some(args.number()?),
+                "--think-time" => options.think_time = Some(args.number()?),
+                "--settings" => options.settings = Some(PathBuf::from(args.value()?)),
+                "--load" => options.load = Some(PathBuf::from(args.value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(args.value()?)),
+                "--host" => options.host = Some(args.value()?),
+                "--join" => options.join = Some(args.value()?),
+                "--watch" => options.watch = Some(args.value()?),
+                "--replay-speed" => {
+                    let value = args.value()?;
+                    options.replay_speed = match value.parse::<f32>() {
+                        Ok(speed) if speed > 0. => Some(speed),
+                        _ => {
+                            return Err(format!(
+                                "{} expects a positive number, got `{}`",
+                                flag, value
+                            ))
+                        }
+                    }
+                }
+                "--ai" => {
+                    options.ai = Some(match args.value()?.as_str() {
+                        "red" => ElementType::Red,
+                        "blue" => ElementType::Blue,
+                        other => return Err(format!("unknown side `{}`, use red or blue", other)),
+                    })
+                }
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Loads the settings file and applies the board options on top of it
+    pub fn settings(&self) -> Result<Settings, String> {
+        let path = self
+            .settings
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(SETTINGS_PATH));
+        let mut settings = match Settings::load(&path) {
+            Ok(settings) => settings,
+            // Without an explicit file the defaults are fine
+            Err(err) if self.settings.is_some() => {
+                return Err(format!("{}: {}", path.display(), err))
+            }
+            Err(err) => {
+                // Каталог сообщений ещё не выбран, он сам задаётся в настройках
+                eprintln!("{}: {}, using the default settings", path.display(), err);
+                Settings::default()
+            }
+        };
+        settings.width = self.width.unwrap_or(settings.width);
+        settings.height = self.height.unwrap_or(settings.height);
+        settings.win_length = self.win_length.unwrap_or(settings.win_length);
+        settings.validate().map_err(|err| err.to_string())?;
+        Ok(settings)
+    }
+
+    /// Reads the game given with `--load` or `--replay`, or the address of
+    /// a network game
+    pub fn start(&self) -> Result<Start, String> {
+        let load = |path: &PathBuf| {
+            GameRecord::load(path).map_err(|err| format!("{}: {}", path.display(), err))
+        };
+        let flags = [
+            ("--load", self.load.is_some()),
+            ("--replay", self.replay.is_some()),
+            ("--host", self.host.is_some()),
+            ("--join", self.join.is_some()),
+            ("--watch", self.watch.is_some()),
+        ];
+        let mut given = flags
+            .iter()
+            .filter(|(_, given)| *given)
+            .map(|(flag, _)| flag);
+        if let (Some(first), Some(second)) = (given.next(), given.next()) {
+            return Err(format!("{} and {} cannot be used together", first, second));
+        }
+        let network = self.host.is_some() || self.join.is_some() || self.watch.is_some();
+        if self.ai.is_some() && network {
+            return Err("--ai cannot be used in a network game".to_string());
+        }
+        if let Some(path) = &self.load {
+            load(path).map(Start::Continue)
+        } else if let Some(path) = &self.replay {
+            load(path).map(Start::Replay)
+        } else if let Some(addr) = &self.host {
+            Ok(Start::Host(addr.clone()))
+        } else if let Some(addr) = &self.join {
+            Ok(Start::Join(addr.clone()))
+        } else if let Some(addr) = &self.watch {
+            Ok(Start::Watch(addr.clone()))
+        } else {
+            Ok(Start::NewGame)
+        }
+    }
+
+    pub fn ai_config(&self) -> AiConfig {
+        let default = AiConfig::default();
+        AiConfig {
+            depth: self.depth.unwrap_or(default.depth),
+            time_budget: self
+                .think_time
+                .map(|ms| Duration::from_millis(ms.into()))
+                .or(default.time_budget),
+        }
+    }
+}
+
+/// Messages in the language from the settings, else in the system language,
+/// else in English
+pub fn catalog(language: Option<&str>) -> Catalog {
+    if let Some(language) = language {
+        match Catalog::load(LOCALES_PATH, language) {
+            Ok(catalog) => return catalog,
+            Err(err) => eprintln!("{}/{}.lang: {}", LOCALES_PATH, language, err),
+        }
+    }
+    // Для языков без перевода молча переходим на английский
+    let system =
+        i18n::system_language().and_then(|language| Catalog::load(LOCALES_PATH, &language).ok());
+    if let Some(catalog) = system {
+        return catalog;
+    }
+    Catalog::load(LOCALES_PATH, "en").unwrap_or_else(|err| {
+        eprintln!("{}/en.lang: {}", LOCALES_PATH, err);
+        Catalog::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use connect_four::{GameState, MoveHistory};
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn values_can_follow_the_flag_or_an_equals_sign() {
+        let options = parse(&[
+            "--width=9",
+            "--height",
+            "7",
+            "--ai=blue",
+            "--host=0.0.0.0:7654",
+        ])
+        .unwrap();
+        assert_eq!(options.width, Some(9));
+        assert_eq!(options.height, Some(7));
+        assert_eq!(options.ai, Some(ElementType::Blue));
+        assert_eq!(options.host.as_deref(), Some("0.0.0.0:7654"));
+    }
+
+    #[test]
+    fn a_flag_without_its_value_is_refused() {
+        assert_eq!(parse(&["--width"]).unwrap_err(), "--width needs a value");
+        assert_eq!(
+            parse(&["--depth", "deep"]).unwrap_err(),
+            "--depth expects a number, got `deep`"
+        );
+    }
+
+    #[test]
+    fn unknown_flags_are_refused() {
+        assert_eq!(
+            parse(&["--colour", "red"]).unwrap_err(),
+            "unknown option `--colour`"
+        );
+        assert_eq!(
+            parse(&["--width9"]).unwrap_err(),
+            "unknown option `--width9`"
+        );
+    }
+
+    #[test]
+    fn a_loaded_game_keeps_its_own_board() {
+        let mut game = GameState::default();
+        let mut history = MoveHistory::new();
+        history.play(&mut game, 3).unwrap();
+        let record = GameRecord::new(&Settings::default(), &history, &game);
+        let path = env::temp_dir().join(format!("connect-four-cli-{}.c4", std::process::id()));
+        record.save(&path).unwrap();
+
+        let load = path.to_str().unwrap();
+        let options = parse(&["--load", load, "--width", "9", "--height=8"]).unwrap();
+        let start = options.start();
+        fs::remove_file(&path).unwrap();
+        let start = start.unwrap();
+        assert!(matches!(&start, Start::Continue(loaded) if loaded.moves == [3]));
+
+        let settings = start.settings(Settings {
+            width: 9,
+            height: 8,
+            ..Settings::default()
+        });
+        assert_eq!((settings.width, settings.height), (7, 6));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::board::{Board, ElemError, ElementType};
+
+/// How a finished game ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win(ElementType),
+    Draw,
+}
+
+/// A piece dropped by a player
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub player: ElementType,
+    pub column: u32,
+    /// Row where the piece landed
+    pub row: u32,
+}
+
+/// A board together with whose turn it is and how the game ended
+#[derive(Clone, Debug)]
+pub struct GameState {
+    board: Board,
+    turn: ElementType,
+    outcome: Option<Outcome>,
+}
+
+impl Default for GameState {
+    fn default() -> GameState {
+        GameState::new(Board::default())
+    }
+}
+
+impl GameState {
+    /// Starts a game on the given board, red moves first
+    pub fn new(board: Board) -> GameState {
+        GameState {
+            board,
+            turn: ElementType::Red,
+            outcome: None,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The player who makes the next move
+    pub fn turn(&self) -> ElementType {
+        self.turn
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Clears the board and gives the first move back to red
+    pub fn reset(&mut self) {
+        self.board.clear();
+        self.turn = ElementType::Red;
+        self.outcome = None;
+    }
+
+    /// Drops a piece of the current player into the column and passes the turn.
+    /// The turn stays with the same player when the move is rejected.
+    ///
+    /// The game is drawn once the board is full and nobody has a line.
+    pub fn play(&mut self, column: u32) -> Result<Move, ElemError> {
+        if self.is_over() {
+            return Err(ElemError::GameAlreadyOver);
+        }
+        let row = self.board.add_at_column(column, self.turn)?;
+        let played = Move {
+            player: self.turn,
+            column,
+            row,
+        };
+        if !self.board.get_matches().is_empty() {
+            self.outcome = Some(Outcome::Win(self.turn));
+        } else if self.board.is_full() {
+            self.outcome = Some(Outcome::Draw);
+        }
+        self.turn = self.turn.other();
+        Ok(played)
+    }
+
+    /// Ends the game as a win of the other player, on either player's turn
+    pub fn resign(&mut self, player: ElementType) -> Result<(), ElemError> {
+        if self.is_over() {
+            return Err(ElemError::GameAlreadyOver);
+        }
+        self.outcome = Some(Outcome::Win(player.other()));
+        Ok(())
+    }
+
+    /// Takes the top piece out of the column and gives the turn back to its owner.
+    /// Meant for the last move played, as the board does not know the order of moves.
+    pub fn undo(&mut self, column: u32) -> Result<Move, ElemError> {
+        let (player, row) = self.board.remove_top(column)?;
+        self.turn = player;
+        let bits = self.board.bits();
+        self.outcome = [ElementType::Red, ElementType::Blue]
+            .into_iter()
+            .find(|&player| bits.is_win(player))
+            .map(Outcome::Win);
+        Ok(Move {
+            player,
+            column,
+            row,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_full_board_without_a_line_is_a_draw() {
+        let columns = [
+            3, 3, 1, 6, 5, 0, 1, 1, 4, 2, 6, 6, 1, 4, 1, 2, 3, 1, 4, 3, 4, 4, 5, 2, 3, 6, 3, 0, 6,
+            4, 2, 6, 0, 5, 5, 5, 5, 2, 0, 2, 0, 0,
+        ];
+        let mut game = GameState::default();
+        for &column in &columns[..41] {
+            game.play(column).unwrap();
+        }
+        assert_eq!(game.outcome(), None);
+        game.play(columns[41]).unwrap();
+        assert!(game.board().is_full());
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn undo_gives_back_the_turn_and_reopens_the_game() {
+        let mut game = GameState::default();
+        game.play(3).unwrap();
+        let played = game.undo(3).unwrap();
+        assert_eq!((played.player, played.row), (ElementType::Red, 0));
+        assert_eq!(game.turn(), ElementType::Red);
+        assert!(game.board().is_empty());
+
+        for column in [0, 1, 0, 1, 0, 1, 0] {
+            game.play(column).unwrap();
+        }
+        assert!(game.is_over());
+        game.undo(0).unwrap();
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.turn(), ElementType::Red);
+        game.play(2).unwrap();
+        assert_eq!(game.turn(), ElementType::Blue);
+        assert_eq!(game.undo(4), Err(ElemError::NoElem));
+    }
+
+    #[test]
+    fn no_move_is_played_after_the_end() {
+        let mut game = GameState::default();
+        for column in [0, 1, 0, 1, 0, 1, 0] {
+            game.play(column).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Win(ElementType::Red)));
+        assert_eq!(game.play(2), Err(ElemError::GameAlreadyOver));
+        assert_eq!(
+            game.resign(ElementType::Blue),
+            Err(ElemError::GameAlreadyOver)
+        );
+        assert_eq!(game.board().len(), 7);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::board::ElemError;
+use crate::game::{GameState, Move};
+
+/// Moves of a game in the order they were played.
+///
+/// Undone moves are kept until a new move is played, so they can be redone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoveHistory {
+    moves: Vec<Move>,
+    /// Number of moves that are on the board, the rest were undone
+    played: usize,
+}
+
+impl MoveHistory {
+    pub fn new() -> MoveHistory {
+        MoveHistory::default()
+    }
+
+    /// Moves that are currently on the board
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[..self.played]
+    }
+
+    pub fn len(&self) -> usize {
+        self.played
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.played == 0
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.played > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.played < self.moves.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.moves.clear();
+        self.played = 0;
+    }
+
+    /// Records a move played on the board, forgetting the undone ones
+    pub fn push(&mut self, played: Move) {
+        self.moves.truncate(self.played);
+        self.moves.push(played);
+        self.played += 1;
+    }
+
+    /// Plays the column in the game and records the move
+    pub fn play(&mut self, game: &mut GameState, column: u32) -> Result<Move, ElemError> {
+        let played = game.play(column)?;
+        self.push(played);
+        Ok(played)
+    }
+
+    /// Takes the last move back, the game must be the one the moves were played in
+    pub fn undo(&mut self, game: &mut GameState) -> Option<Move> {
+        let last = *self.moves().last()?;
+        game.undo(last.column).ok()?;
+        self.played -= 1;
+        Some(last)
+    }
+
+    /// Plays the last undone move again
+    pub fn redo(&mut self, game: &mut GameState) -> Option<Move> {
+        let next = *self.moves.get(self.played)?;
+        game.play(next.column).ok()?;
+        self.played += 1;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ElementType;
+
+    #[test]
+    fn undo_then_redo_restores_the_move() {
+        let mut game = GameState::default();
+        let mut history = MoveHistory::new();
+        history.play(&mut game, 3).unwrap();
+        let played = history.play(&mut game, 4).unwrap();
+        let before = game.board().clone();
+
+        assert_eq!(history.undo(&mut game), Some(played));
+        assert_eq!(history.moves().len(), 1);
+        assert!(history.can_redo());
+        assert_eq!(game.board().len(), 1);
+
+        assert_eq!(history.redo(&mut game), Some(played));
+        assert_eq!(*game.board(), before);
+        assert_eq!(game.turn(), ElementType::Red);
+        assert_eq!(history.len(), 2);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn a_new_move_after_undo_forgets_the_undone_ones() {
+        let mut game = GameState::default();
+        let mut history = MoveHistory::new();
+        for column in [0, 1, 2] {
+            history.play(&mut game, column).unwrap();
+        }
+        history.undo(&mut game).unwrap();
+        history.undo(&mut game).unwrap();
+        history.play(&mut game, 5).unwrap();
+
+        let columns: Vec<u32> = history.moves().iter().map(|m| m.column).collect();
+        assert_eq!(columns, [0, 5]);
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(&mut game), None);
+    }
+
+    #[test]
+    fn an_empty_history_has_nothing_to_undo_or_redo() {
+        let mut game = GameState::default();
+        let mut history = MoveHistory::new();
+        assert_eq!(history.undo(&mut game), None);
+        assert_eq!(history.redo(&mut game), None);
+        assert!(history.is_empty());
+        assert!(game.board().is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+//! Translated messages for the front-ends.
+//!
+//! Every language has its own catalog file with one `key = text` pair per
+//! line, lines starting with `#` are comments. `\n` in a text is a line break
+//! and `{name}` is replaced with the argument of that name:
+//!
+//! ```text
+//! # assets/locales/en.lang
+//! player.red = red
+//! game.win = The {player} player wins!
+//! ```
+//!
+//! Errors of the library are not spliced into the messages as they are
+//! displayed, every variant is told through its own `error.*` key, see
+//! [`Localize`].
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::board::{ElemError, ElementType};
+use crate::net::{MessageError, NetError};
+use crate::notation::{LoadError, ParseError, ParseErrorKind};
+
+/// Messages of one language
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    language: String,
+    messages: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(io::Error),
+    /// The line is not a `key = text` pair
+    Syntax { line: usize },
+    /// The key was already given on an earlier line
+    Duplicate { line: usize, key: String },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io(err) => write!(f, "{}", err),
+            CatalogError::Syntax { line } => write!(f, "line {}: expected `key = text`", line),
+            CatalogError::Duplicate { line, key } => {
+                write!(f, "line {}: `{}` is given twice", line, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl From<io::Error> for CatalogError {
+    fn from(err: io::Error) -> CatalogError {
+        CatalogError::Io(err)
+    }
+}
+
+impl Catalog {
+    /// Reads `<language>.lang` from the directory
+    pub fn load(dir: impl AsRef<Path>, language: &str) -> Result<Catalog, CatalogError> {
+        let path = dir.as_ref().join(format!("{}.lang", language));
+        Catalog::parse(language, &fs::read_to_string(path)?)
+    }
+
+    pub fn parse(language: &str, text: &str) -> Result<Catalog, CatalogError> {
+        let mut messages = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, message) = line
+                .split_once('=')
+                .ok_or(CatalogError::Syntax { line: line_number })?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(CatalogError::Syntax { line: line_number });
+            }
+            let message = message.trim().replace("\\n", "\n");
+            if messages.insert(key.to_string(), message).is_some() {
+                return Err(CatalogError::Duplicate {
+                    line: line_number,
+                    key: key.to_string(),
+                });
+            }
+        }
+        Ok(Catalog {
+            language: language.to_string(),
+            messages,
+        })
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The message for the key, or the key itself when the catalog has none
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.messages.get(key).map_or(key, String::as_str)
+    }
+
+    /// Name of the player of the given colour
+    pub fn player(&self, player: ElementType) -> &str {
+        self.get(match player {
+            ElementType::Red => "player.red",
+            ElementType::Blue => "player.blue",
+        })
+    }
+
+    /// The message with every `{name}` replaced by the argument of that name
+    pub fn format(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut message = self.get(key).to_string();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        message
+    }
+
+    /// The error in the language of the catalog
+    pub fn error(&self, err: &impl Localize) -> String {
+        err.localize(self)
+    }
+}
+
+/// An error with a catalog key for every variant
+pub trait Localize {
+    fn localize(&self, catalog: &Catalog) -> String;
+}
+
+// Only the common kinds are translated, the rest keeps the text of the system
+impl Localize for io::Error {
+    fn localize(&self, catalog: &Catalog) -> String {
+        let key = match self.kind() {
+            io::ErrorKind::NotFound => "error.io.not_found",
+            io::ErrorKind::PermissionDenied => "error.io.permission_denied",
+            io::ErrorKind::ConnectionRefused => "error.io.connection_refused",
+            io::ErrorKind::TimedOut => "error.io.timed_out",
+            io::ErrorKind::AddrInUse => "error.io.address_in_use",
+            _ => "error.io.other",
+        };
+        catalog.format(key, &[("error", self)])
+    }
+}
+
+impl Localize for ElemError {
+    fn localize(&self, catalog: &Catalog) -> String {
+        catalog
+            .get(match self {
+                ElemError::NoElem => "error.no_elem",
+                ElemError::OutOfBounds => "error.out_of_bounds",
+                ElemError::ColumnFull => "error.column_full",
+                ElemError::GameAlreadyOver => "error.game_over",
+            })
+            .to_string()
+    }
+}
+
+impl Localize for MessageError {
+    fn localize(&self, catalog: &Catalog) -> String {
+        match self {
+            MessageError::Empty => catalog.get("error.message.empty").to_string(),
+            MessageError::Unknown(name) => {
+                catalog.format("error.message.unknown", &[("name", name)])
+            }
+            MessageError::InvalidArguments(name) => {
+                catalog.format("error.message.arguments", &[("name", name)])
+            }
+        }
+    }
+}
+
+impl Localize for NetError {
+    fn localize(&self, catalog: &Catalog) -> String {
+        match self {
+            NetError::Io(err) => err.localize(catalog),
+            NetError::Message(err) => err.localize(catalog),
+            NetError::Closed => catalog.get("error.net.closed").to_string(),
+            NetError::Version(version) => {
+                catalog.format("error.net.version", &[("version", version)])
+            }
+            NetError::Unexpected(message) => {
+                catalog.format("error.net.unexpected", &[("message", message)])
+            }
+            NetError::IllegalMove(err) => {
+                catalog.format("error.illegal_move", &[("error", &err.localize(catalog))])
+            }
+            NetError::NotYourTurn => catalog.get("error.net.not_your_turn").to_string(),
+        }
+    }
+}
+
+impl Localize for ParseErrorKind {
+    fn localize(&self, catalog: &Catalog) -> String {
+        match self {
+            ParseErrorKind::MalformedTag => catalog.get("error.parse.malformed_tag").to_string(),
+            ParseErrorKind::UnknownTag(name) => {
+                catalog.format("error.parse.unknown_tag", &[("name", name)])
+            }
+            ParseErrorKind::InvalidSize(value) => {
+                catalog.format("error.parse.invalid_size", &[("value", value)])
+            }
+            ParseErrorKind::InvalidWinLength(value) => {
+                catalog.format("error.parse.invalid_win_length", &[("value", value)])
+            }
+            ParseErrorKind::InvalidResult(value) => {
+                catalog.format("error.parse.invalid_result", &[("value", value)])
+            }
+            // The reason comes from the settings in English, so it is left out
+            ParseErrorKind::InvalidSettings(_) => {
+                catalog.get("error.parse.invalid_settings").to_string()
+            }
+            ParseErrorKind::UnexpectedToken(token) => {
+                catalog.format("error.parse.unexpected_token", &[("token", token)])
+            }
+            ParseErrorKind::IllegalMove(err) => {
+                catalog.format("error.illegal_move", &[("error", &err.localize(catalog))])
+            }
+            ParseErrorKind::TrailingToken(token) => {
+                catalog.format("error.parse.trailing_token", &[("token", token)])
+            }
+            ParseErrorKind::ResultMismatch => {
+                catalog.get("error.parse.result_mismatch").to_string()
+            }
+        }
+    }
+}
+
+impl Localize for ParseError {
+    fn localize(&self, catalog: &Catalog) -> String {
+        catalog.format(
+            "error.parse",
+            &[
+                ("line", &self.line),
+                ("column", &self.column),
+                ("error", &self.kind.localize(catalog)),
+            ],
+        )
+    }
+}
+
+impl Localize for LoadError {
+    fn localize(&self, catalog: &Catalog) -> String {
+        match self {
+            LoadError::Io(err) => err.localize(catalog),
+            LoadError::Parse(err) => err.localize(catalog),
+        }
+    }
+}
+
+/// Language of the user from `LC_ALL`, `LC_MESSAGES` or `LANG`,
+/// `ru_RU.UTF-8` gives `ru`
+pub fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| locale_language(&value))
+}
+
+/// The language part of a locale name, `None` for `C` and `POSIX`
+fn locale_language(locale: &str) -> Option<String> {
+    let language = locale
+        .split(['_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    Some(language).filter(|language| !language.is_empty() && language != "c" && language != "posix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EN: &str = include_str!("../assets/locales/en.lang");
+    const RU: &str = include_str!("../assets/locales/ru.lang");
+
+    #[test]
+    fn parse_skips_comments_and_blank_lines() {
+        let catalog = Catalog::parse(
+            "en",
+            "# a comment\n\n  greeting = Hello, {name}!  \nmenu = one\\ntwo\n",
+        )
+        .unwrap();
+        assert_eq!(catalog.language(), "en");
+        assert_eq!(catalog.get("greeting"), "Hello, {name}!");
+        assert_eq!(catalog.get("menu"), "one\ntwo");
+        assert_eq!(catalog.get("# a comment"), "# a comment");
+    }
+
+    #[test]
+    fn parse_refuses_malformed_lines_and_duplicate_keys() {
+        assert!(matches!(
+            Catalog::parse("en", "a = 1\nno pair here\n"),
+            Err(CatalogError::Syntax { line: 2 })
+        ));
+        assert!(matches!(
+            Catalog::parse("en", " = nameless\n"),
+            Err(CatalogError::Syntax { line: 1 })
+        ));
+        assert!(matches!(
+            Catalog::parse("en", "a = 1\n# a = 2\na = 3\n"),
+            Err(CatalogError::Duplicate { line: 3, key }) if key == "a"
+        ));
+    }
+
+    #[test]
+    fn format_replaces_every_placeholder() {
+        let catalog = Catalog::parse("en", "score = {red} — {blue}, {red} again").unwrap();
+        assert_eq!(
+            catalog.format("score", &[("red", &3), ("blue", &"two")]),
+            "3 — two, 3 again"
+        );
+        assert_eq!(catalog.format("score", &[]), "{red} — {blue}, {red} again");
+    }
+
+    #[test]
+    fn missing_messages_fall_back_to_the_key() {
+        let catalog = Catalog::parse("en", "player.red = red").unwrap();
+        assert_eq!(catalog.get("player.blue"), "player.blue");
+        assert_eq!(catalog.player(ElementType::Blue), "player.blue");
+        assert_eq!(catalog.format("hud.moves", &[("count", &4)]), "hud.moves");
+    }
+
+    #[test]
+    fn the_language_is_taken_from_the_locale_name() {
+        assert_eq!(locale_language("ru_RU.UTF-8").as_deref(), Some("ru"));
+        assert_eq!(locale_language("en_GB").as_deref(), Some("en"));
+        assert_eq!(locale_language("de.UTF-8").as_deref(), Some("de"));
+        assert_eq!(locale_language("sr@latin").as_deref(), Some("sr"));
+        assert_eq!(locale_language("C.UTF-8"), None);
+        assert_eq!(locale_language("POSIX"), None);
+        assert_eq!(locale_language(""), None);
+    }
+
+    #[test]
+    fn every_language_has_the_same_keys() {
+        let keys = |text| {
+            let catalog = Catalog::parse("", text).unwrap();
+            let mut keys: Vec<String> = catalog.messages.into_keys().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(EN), keys(RU));
+    }
+
+    #[test]
+    fn errors_are_told_with_their_own_keys() {
+        let en = Catalog::parse("en", EN).unwrap();
+        let ru = Catalog::parse("ru", RU).unwrap();
+        assert_eq!(en.error(&ElemError::ColumnFull), "the column is full");
+        assert_eq!(ru.error(&ElemError::ColumnFull), "столбец заполнен");
+
+        let err = ParseError {
+            line: 9,
+            column: 3,
+            kind: ParseErrorKind::IllegalMove(ElemError::OutOfBounds),
+        };
+        assert_eq!(
+            en.error(&err),
+            "9:3: illegal move: the column is outside of the board"
+        );
+        assert_eq!(
+            ru.error(&NetError::Version(3)),
+            "версия протокола 3 не поддерживается"
+        );
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(ru.error(&NetError::Io(refused)), "в подключении отказано");
+    }
+}
@@ -0,0 +1,24 @@
+//! Rules of Connect Four without any rendering or windowing.
+//!
+//! The Bevy application in `main.rs` is only a front-end over these types,
+//! so bots, tools and tests can use the rules directly.
+
+pub mod ai;
+pub mod args;
+mod bitboard;
+mod board;
+mod game;
+mod history;
+pub mod i18n;
+pub mod net;
+pub mod notation;
+pub mod server;
+mod settings;
+pub mod solver;
+pub mod ws;
+
+pub use bitboard::{BitBoard, Mask};
+pub use board::{Board, ElemError, ElementType, Match, Matches, Pos};
+pub use game::{GameState, Move, Outcome};
+pub use history::MoveHistory;
+pub use settings::{Settings, SettingsError};
@@ -0,0 +1,804 @@
+//! Two players on different machines, connected over TCP.
+//!
+//! Each message is one line of UTF-8 text ending with `\n`. The first word
+//! names the message and the rest are its arguments:
+//!
+//! ```text
+//! hello 2 red          protocol version and the side the sender plays
+//! sync 7x6 4 4 4 5 3   board size, win length and the columns played so far
+//! move 5               a piece of the sender dropped into the column
+//! resign               the sender gives up the game
+//! rematch              the sender wants a new game
+//! watch 2              protocol version of a spectator, instead of `hello`
+//! resigned blue        the side that gave up, sent to spectators
+//! spectators 3         number of spectators, sent by the host
+//! ```
+//!
+//! Columns are counted from 1 on the left, like in the game notation.
+//!
+//! The host chooses the sides, a player hosting a game plays red. Once the
+//! guest connects, the host sends `hello` with its side and the guest answers
+//! with `hello` for the other side. Then the host sends `sync` with the game
+//! to play, it may send `sync` again later to replace the game of the guest.
+//! After that each side sends only the moves of its own side and only on its
+//! own turn. Either side may resign at any time. The board is cleared once
+//! both sides have sent `rematch`.
+//!
+//! Everyone who connects to the host after the guest, or answers its
+//! `hello` with `watch`, is a spectator. A spectator gets `sync` with the
+//! game being played, followed by `resigned` when a player has already given
+//! up, then `move` for the moves of both sides, `resigned` and a new `sync`
+//! after a rematch. Spectators cannot change the game:
+//! whatever they send is answered with `sync`. The host tells the guest and
+//! the spectators how many people watch with `spectators`.
+//!
+//! A message that breaks these rules ends a `Session`, both sides keep their
+//! own copy of the game and cannot recover from a difference. The dedicated
+//! server in `server` is the host for both of its players, it refuses such a
+//! message and sends `sync` with the real game instead.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::{ElemError, ElementType};
+use crate::game::{GameState, Move, Outcome};
+use crate::history::MoveHistory;
+use crate::settings::Settings;
+
+/// Version sent in `hello`, peers with another version are refused
+pub const VERSION: u32 = 2;
+
+/// The side of a player hosting the game
+pub const HOST_SIDE: ElementType = ElementType::Red;
+
+/// How long a new connection has to answer `hello`
+pub const GREETING_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Hello {
+        version: u32,
+        side: ElementType,
+    },
+    /// The language of the settings is not sent
+    Sync {
+        settings: Settings,
+        moves: Vec<u32>,
+    },
+    /// Column counted from 0
+    Move {
+        column: u32,
+    },
+    Resign,
+    Rematch,
+    Watch {
+        version: u32,
+    },
+    Resigned {
+        side: ElementType,
+    },
+    Spectators {
+        count: u32,
+    },
+}
+
+/// Why a line is not a message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MessageError {
+    Empty,
+    Unknown(String),
+    /// The arguments do not fit the message with this name
+    InvalidArguments(String),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::Empty => write!(f, "empty message"),
+            MessageError::Unknown(name) => write!(f, "unknown message `{}`", name),
+            MessageError::InvalidArguments(name) => write!(f, "invalid arguments of `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Message(MessageError),
+    /// The peer has closed the connection
+    Closed,
+    /// The peer speaks another version of the protocol
+    Version(u32),
+    /// A message that is not allowed at this point, like a move out of turn
+    Unexpected(Message),
+    /// A move that cannot be played on the board
+    IllegalMove(ElemError),
+    /// A local move on the turn of the peer
+    NotYourTurn,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(err) => write!(f, "{}", err),
+            NetError::Message(err) => write!(f, "{}", err),
+            NetError::Closed => write!(f, "the connection is closed"),
+            NetError::Version(version) => {
+                write!(f, "protocol version {} is not supported", version)
+            }
+            NetError::Unexpected(message) => write!(f, "unexpected `{}`", message),
+            NetError::IllegalMove(err) => write!(f, "illegal move: {}", err),
+            NetError::NotYourTurn => write!(f, "it is not your turn"),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(err: io::Error) -> NetError {
+        NetError::Io(err)
+    }
+}
+
+impl From<MessageError> for NetError {
+    fn from(err: MessageError) -> NetError {
+        NetError::Message(err)
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Hello { version, side } => write!(f, "hello {} {}", version, side_name(*side)),
+            Message::Sync { settings, moves } => {
+                write!(
+                    f,
+                    "sync {}x{} {}",
+                    settings.width, settings.height, settings.win_length
+                )?;
+                for column in moves {
+                    write!(f, " {}", column + 1)?;
+                }
+                Ok(())
+            }
+            Message::Move { column } => write!(f, "move {}", column + 1),
+            Message::Resign => write!(f, "resign"),
+            Message::Rematch => write!(f, "rematch"),
+            Message::Watch { version } => write!(f, "watch {}", version),
+            Message::Resigned { side } => write!(f, "resigned {}", side_name(*side)),
+            Message::Spectators { count } => write!(f, "spectators {}", count),
+        }
+    }
+}
+
+impl std::str::FromStr for Message {
+    type Err = MessageError;
+
+    fn from_str(line: &str) -> Result<Message, MessageError> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or(MessageError::Empty)?;
+        let invalid = || MessageError::InvalidArguments(name.to_string());
+        let column = |word: &str| match word.parse::<u32>() {
+            Ok(column) if column > 0 => Ok(column - 1),
+            _ => Err(invalid()),
+        };
+        let args: Vec<&str> = words.collect();
+        let message = match (name, args.as_slice()) {
+            ("hello", [version, side]) => Message::Hello {
+                version: version.parse().map_err(|_| invalid())?,
+                side: parse_side(side).ok_or_else(invalid)?,
+            },
+            ("sync", [size, win_length, moves @ ..]) => {
+                let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+                let settings = Settings {
+                    width: width.parse().map_err(|_| invalid())?,
+                    height: height.parse().map_err(|_| invalid())?,
+                    win_length: win_length.parse().map_err(|_| invalid())?,
+                    language: None,
+                };
+                settings.validate().map_err(|_| invalid())?;
+                Message::Sync {
+                    settings,
+                    moves: moves
+                        .iter()
+                        .map(|word| column(word))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+            ("move", [word]) => Message::Move {
+                column: column(word)?,
+            },
+            ("resign", []) => Message::Resign,
+            ("rematch", []) => Message::Rematch,
+            ("watch", [version]) => Message::Watch {
+                version: version.parse().map_err(|_| invalid())?,
+            },
+            ("resigned", [side]) => Message::Resigned {
+                side: parse_side(side).ok_or_else(invalid)?,
+            },
+            ("spectators", [count]) => Message::Spectators {
+                count: count.parse().map_err(|_| invalid())?,
+            },
+            (
+                "hello" | "sync" | "move" | "resign" | "rematch" | "watch" | "resigned"
+                | "spectators",
+                _,
+            ) => return Err(invalid()),
+            _ => return Err(MessageError::Unknown(name.to_string())),
+        };
+        Ok(message)
+    }
+}
+
+fn side_name(side: ElementType) -> &'static str {
+    match side {
+        ElementType::Red => "red",
+        ElementType::Blue => "blue",
+    }
+}
+
+fn parse_side(word: &str) -> Option<ElementType> {
+    match word {
+        "red" => Some(ElementType::Red),
+        "blue" => Some(ElementType::Blue),
+        _ => None,
+    }
+}
+
+/// One end of a connection. Lines are read by a background thread, so
+/// waiting for the peer never blocks the caller of `try_recv`.
+pub struct Peer {
+    stream: TcpStream,
+    incoming: Receiver<Result<Message, NetError>>,
+}
+
+impl Peer {
+    pub fn new(stream: TcpStream) -> io::Result<Peer> {
+        let (sender, incoming) = mpsc::channel();
+        read_messages(&stream, move |message| sender.send(message).is_ok())?;
+        Ok(Peer { stream, incoming })
+    }
+
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Peer> {
+        Peer::new(TcpStream::connect(addr)?)
+    }
+
+    /// Waits for the next connection to the listener
+    pub fn accept(listener: &TcpListener) -> io::Result<Peer> {
+        Peer::new(listener.accept()?.0)
+    }
+
+    pub fn send(&self, message: &Message) -> Result<(), NetError> {
+        write_message(&self.stream, message)?;
+        Ok(())
+    }
+
+    /// Waits for the next message
+    pub fn recv(&self) -> Result<Message, NetError> {
+        self.incoming.recv().unwrap_or(Err(NetError::Closed))
+    }
+
+    /// Waits for the next message, an `Io` error of `TimedOut` kind when none
+    /// comes in time
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Message, NetError> {
+        match self.incoming.recv_timeout(timeout) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => Err(NetError::Io(io::ErrorKind::TimedOut.into())),
+            Err(RecvTimeoutError::Disconnected) => Err(NetError::Closed),
+        }
+    }
+
+    /// The next message if one has arrived
+    pub fn try_recv(&self) -> Result<Option<Message>, NetError> {
+        match self.incoming.try_recv() {
+            Ok(message) => message.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(NetError::Closed),
+        }
+    }
+}
+
+impl Drop for Peer {
+    // Shutting down both directions also ends the reading thread
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+pub(crate) fn write_message(mut stream: &TcpStream, message: &Message) -> io::Result<()> {
+    stream.write_all(format!("{}\n", message).as_bytes())
+}
+
+/// Passes every message from the stream to `receive` in a background thread.
+/// Reading stops after the first error, `NetError::Closed` when the stream
+/// ends, or once `receive` returns `false`.
+pub(crate) fn read_messages(
+    stream: &TcpStream,
+    mut receive: impl FnMut(Result<Message, NetError>) -> bool + Send + 'static,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let reader = BufReader::new(stream.try_clone()?);
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let message = match line {
+                Ok(line) => line.parse().map_err(NetError::Message),
+                Err(err) => Err(NetError::Io(err)),
+            };
+            // Nothing after a broken line can be trusted
+            let failed = message.is_err();
+            if !receive(message) || failed {
+                return;
+            }
+        }
+        receive(Err(NetError::Closed));
+    });
+    Ok(())
+}
+
+/// What the peer did, as returned by `Session::poll`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// The move is already played on the game of the session
+    Moved(Move),
+    Resigned,
+    /// The peer wants a rematch, `Session::rematch` accepts it
+    RematchOffered,
+    /// Both sides asked for a rematch and the board is empty again
+    Restarted,
+    /// The host replaced the game
+    Synced,
+    /// The number of spectators changed
+    Spectators(u32),
+}
+
+/// A game between this side and the peer. The session keeps its own copy
+/// of the game to check every move before it is sent or accepted.
+pub struct Session {
+    peer: Peer,
+    side: ElementType,
+    /// Only the guest accepts `sync`
+    hosting: bool,
+    settings: Settings,
+    game: GameState,
+    history: MoveHistory,
+    /// Whether this side has asked for a rematch
+    rematch_sent: bool,
+    /// Whether the peer has asked for a rematch
+    rematch_received: bool,
+    /// Spectators of the game, only the host has them
+    audience: Option<Audience>,
+}
+
+impl Session {
+    /// Waits for a guest and starts a new game with these settings. Later
+    /// connections to the listener are spectators, `poll` lets them in.
+    /// Connections that close or do not speak this version are dropped.
+    pub fn host(listener: &TcpListener, settings: &Settings) -> Result<Session, NetError> {
+        let side = HOST_SIDE;
+        let settings = Settings {
+            language: None,
+            ..settings.clone()
+        };
+        let sync = Message::Sync {
+            settings: settings.clone(),
+            moves: Vec::new(),
+        };
+        let hello = Message::Hello {
+            version: VERSION,
+            side,
+        };
+        // Spectators may come before the guest
+        let mut spectators = Vec::new();
+        let peer = loop {
+            // Only the listener failing ends hosting, a bad connection is dropped
+            let Ok(peer) = Peer::new(listener.accept()?.0) else {
+                continue;
+            };
+            // A client that stays silent is dropped as well
+            match peer
+                .send(&hello)
+                .and_then(|()| peer.recv_timeout(GREETING_TIMEOUT))
+            {
+                Ok(Message::Hello {
+                    version,
+                    side: guest,
+                }) if version == VERSION && guest == side.other() && peer.send(&sync).is_ok() => {
+                    break peer
+                }
+                Ok(Message::Watch { version })
+                    if version == VERSION && peer.send(&sync).is_ok() =>
+                {
+                    spectators.push(peer);
+                }
+                // Closed, another version or not a guest at all
+                _ => {}
+            }
+        };
+        let mut session = Session::new(peer, side, true, settings);
+        session.audience = Some(Audience::new(listener, side, spectators)?);
+        Ok(session)
+    }
+
+    /// Connects to the host and waits for the game it sends
+    pub fn join(addr: impl ToSocketAddrs) -> Result<Session, NetError> {
+        Session::join_peer(Peer::connect(addr)?)
+    }
+
+    /// Joins the host at the other end of a connection that is already open
+    pub fn join_peer(peer: Peer) -> Result<Session, NetError> {
+        let side = match peer.recv()? {
+            Message::Hello { version, .. } if version != VERSION => {
+                return Err(NetError::Version(version))
+            }
+            Message::Hello { side, .. } => side.other(),
+            message => return Err(NetError::Unexpected(message)),
+        };
+        peer.send(&Message::Hello {
+            version: VERSION,
+            side,
+        })?;
+        let mut session = Session::new(peer, side, false, Settings::default());
+        match session.peer.recv()? {
+            Message::Sync { settings, moves } => session.sync(settings, &moves)?,
+            message => return Err(NetError::Unexpected(message)),
+        }
+        Ok(session)
+    }
+
+    fn new(peer: Peer, side: ElementType, hosting: bool, settings: Settings) -> Session {
+        Session {
+            peer,
+            side,
+            hosting,
+            game: GameState::new(settings.new_board()),
+            settings,
+            history: MoveHistory::new(),
+            rematch_sent: false,
+            rematch_received: false,
+            audience: None,
+        }
+    }
+
+    /// The side played on this end
+    pub fn side(&self) -> ElementType {
+        self.side
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    pub fn history(&self) -> &MoveHistory {
+        &self.history
+    }
+
+    /// Plays a move of this side and sends it to the peer
+    pub fn play(&mut self, column: u32) -> Result<Move, NetError> {
+        if self.game.turn() != self.side {
+            return Err(NetError::NotYourTurn);
+        }
+        let played = self
+            .history
+            .play(&mut self.game, column)
+            .map_err(NetError::IllegalMove)?;
+        self.peer.send(&Message::Move { column })?;
+        self.show(&Message::Move { column });
+        Ok(played)
+    }
+
+    pub fn resign(&mut self) -> Result<(), NetError> {
+        self.game.resign(self.side).map_err(NetError::IllegalMove)?;
+        self.peer.send(&Message::Resign)?;
+        self.show(&Message::Resigned { side: self.side });
+        Ok(())
+    }
+
+    /// Asks for a rematch, `true` when the peer has already asked and
+    /// the board is empty again
+    pub fn rematch(&mut self) -> Result<bool, NetError> {
+        if !self.rematch_sent {
+            self.peer.send(&Message::Rematch)?;
+            self.rematch_sent = true;
+        }
+        Ok(self.restart_if_agreed())
+    }
+
+    /// Handles the next message of the peer if one has arrived. The host
+    /// also lets in new spectators and refuses what they send.
+    pub fn poll(&mut self) -> Result<Option<SessionEvent>, NetError> {
+        if let Some(count) = self.poll_audience()? {
+            return Ok(Some(SessionEvent::Spectators(count)));
+        }
+        match self.peer.try_recv()? {
+            Some(message) => self.receive(message).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Waits for the next message of the peer and handles it, spectators
+    /// have to wait for `poll`
+    pub fn wait(&mut self) -> Result<SessionEvent, NetError> {
+        let message = self.peer.recv()?;
+        self.receive(message)
+    }
+
+    fn receive(&mut self, message: Message) -> Result<SessionEvent, NetError> {
+        match message {
+            Message::Move { column } if self.game.turn() != self.side => {
+                let played = self
+                    .history
+                    .play(&mut self.game, column)
+                    .map_err(NetError::IllegalMove)?;
+                self.show(&Message::Move { column });
+                Ok(SessionEvent::Moved(played))
+            }
+            Message::Resign => {
+                let side = self.side.other();
+                self.game.resign(side).map_err(NetError::IllegalMove)?;
+                self.show(&Message::Resigned { side });
+                Ok(SessionEvent::Resigned)
+            }
+            Message::Rematch => {
+                self.rematch_received = true;
+                if self.restart_if_agreed() {
+                    Ok(SessionEvent::Restarted)
+                } else {
+                    Ok(SessionEvent::RematchOffered)
+                }
+            }
+            Message::Sync { settings, moves } if !self.hosting => {
+                self.sync(settings, &moves)?;
+                Ok(SessionEvent::Synced)
+            }
+            Message::Spectators { count } if !self.hosting => Ok(SessionEvent::Spectators(count)),
+            message => Err(NetError::Unexpected(message)),
+        }
+    }
+
+    fn sync(&mut self, settings: Settings, moves: &[u32]) -> Result<(), NetError> {
+        (self.game, self.history) = replay(&settings, moves)?;
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Sends the message to the spectators
+    fn show(&mut self, message: &Message) {
+        if let Some(audience) = &mut self.audience {
+            audience.send(message);
+        }
+    }
+
+    /// The number of spectators when it has changed, the guest and the
+    /// spectators learn it as well
+    fn poll_audience(&mut self) -> Result<Option<u32>, NetError> {
+        let Some(audience) = &mut self.audience else {
+            return Ok(None);
+        };
+        let Some(count) =
+            audience.poll(|| spectator_sync(&self.settings, &self.history, &self.game))
+        else {
+            return Ok(None);
+        };
+        audience.send(&Message::Spectators { count });
+        self.peer.send(&Message::Spectators { count })?;
+        Ok(Some(count))
+    }
+
+    fn restart_if_agreed(&mut self) -> bool {
+        if !(self.rematch_sent && self.rematch_received) {
+            return false;
+        }
+        self.rematch_sent = false;
+        self.rematch_received = false;
+        self.game.reset();
+        self.history.clear();
+        self.show(&sync_message(&self.settings, &self.history));
+        true
+    }
+}
+
+/// Read-only clients of a hosted game
+struct Audience {
+    /// Does not block, new spectators are let in by `poll`
+    listener: TcpListener,
+    side: ElementType,
+    /// Connected clients that have not answered `hello` yet, with the time
+    /// when they are dropped
+    pending: Vec<(Peer, Instant)>,
+    spectators: Vec<Peer>,
+    /// The number of spectators last returned by `poll`
+    reported: usize,
+}
+
+impl Audience {
+    fn new(
+        listener: &TcpListener,
+        side: ElementType,
+        spectators: Vec<Peer>,
+    ) -> io::Result<Audience> {
+        let listener = listener.try_clone()?;
+        listener.set_nonblocking(true)?;
+        Ok(Audience {
+            listener,
+            side,
+            pending: Vec::new(),
+            spectators,
+            reported: 0,
+        })
+    }
+
+    /// Lets in new spectators with the game from `sync` and refuses what the
+    /// others send. Returns the number of spectators when it has changed.
+    fn poll(&mut self, sync: impl Fn() -> Vec<Message>) -> Option<u32> {
+        let send_sync = |peer: &Peer| sync().iter().all(|message| peer.send(message).is_ok());
+        while let Ok((stream, _)) = self.listener.accept() {
+            let hello = Message::Hello {
+                version: VERSION,
+                side: self.side,
+            };
+            // Some systems make the connection non-blocking like the listener
+            let peer = stream
+                .set_nonblocking(false)
+                .and_then(|()| Peer::new(stream))
+                .ok()
+                .filter(|peer| peer.send(&hello).is_ok());
+            let deadline = Instant::now() + GREETING_TIMEOUT;
+            self.pending.extend(peer.map(|peer| (peer, deadline)));
+        }
+        for (peer, deadline) in mem::take(&mut self.pending) {
+            match peer.try_recv() {
+                Ok(None) if Instant::now() < deadline => self.pending.push((peer, deadline)),
+                Ok(Some(Message::Watch { version })) if version == VERSION && send_sync(&peer) => {
+                    self.spectators.push(peer);
+                }
+                // Too late, another player or another version, the connection is closed
+                _ => {}
+            }
+        }
+        self.spectators.retain(|peer| match peer.try_recv() {
+            Ok(None) => true,
+            Ok(Some(_)) => send_sync(peer),
+            Err(_) => false,
+        });
+        if self.spectators.len() == self.reported {
+            return None;
+        }
+        self.reported = self.spectators.len();
+        Some(self.reported as u32)
+    }
+
+    /// Those who cannot get the message have left
+    fn send(&mut self, message: &Message) {
+        self.spectators.retain(|peer| peer.send(message).is_ok());
+    }
+}
+
+/// Someone watching a game hosted on another machine. Like a session, the
+/// spectator keeps its own copy of the game and checks every move.
+pub struct Spectator {
+    peer: Peer,
+    settings: Settings,
+    game: GameState,
+    history: MoveHistory,
+}
+
+impl Spectator {
+    /// Connects to the host and waits for the game being played
+    pub fn watch(addr: impl ToSocketAddrs) -> Result<Spectator, NetError> {
+        let peer = Peer::connect(addr)?;
+        match peer.recv()? {
+            Message::Hello { version, .. } if version != VERSION => {
+                return Err(NetError::Version(version))
+            }
+            Message::Hello { .. } => {}
+            message => return Err(NetError::Unexpected(message)),
+        }
+        peer.send(&Message::Watch { version: VERSION })?;
+        match peer.recv()? {
+            Message::Sync { settings, moves } => {
+                let (game, history) = replay(&settings, &moves)?;
+                Ok(Spectator {
+                    peer,
+                    settings,
+                    game,
+                    history,
+                })
+            }
+            message => Err(NetError::Unexpected(message)),
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    pub fn history(&self) -> &MoveHistory {
+        &self.history
+    }
+
+    /// Handles the next message of the host if one has arrived
+    pub fn poll(&mut self) -> Result<Option<SessionEvent>, NetError> {
+        match self.peer.try_recv()? {
+            Some(message) => self.receive(message).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Waits for the next message of the host and handles it
+    pub fn wait(&mut self) -> Result<SessionEvent, NetError> {
+        let message = self.peer.recv()?;
+        self.receive(message)
+    }
+
+    fn receive(&mut self, message: Message) -> Result<SessionEvent, NetError> {
+        match message {
+            Message::Move { column } => {
+                let played = self
+                    .history
+                    .play(&mut self.game, column)
+                    .map_err(NetError::IllegalMove)?;
+                Ok(SessionEvent::Moved(played))
+            }
+            Message::Resigned { side } => {
+                self.game.resign(side).map_err(NetError::IllegalMove)?;
+                Ok(SessionEvent::Resigned)
+            }
+            Message::Sync { settings, moves } => {
+                (self.game, self.history) = replay(&settings, &moves)?;
+                self.settings = settings;
+                Ok(SessionEvent::Synced)
+            }
+            Message::Spectators { count } => Ok(SessionEvent::Spectators(count)),
+            message => Err(NetError::Unexpected(message)),
+        }
+    }
+}
+
+fn sync_message(settings: &Settings, history: &MoveHistory) -> Message {
+    Message::Sync {
+        settings: settings.clone(),
+        moves: history.moves().iter().map(|played| played.column).collect(),
+    }
+}
+
+/// What a new spectator gets: the game, and who gave up when it ended
+/// with a resignation, which the moves alone do not show
+fn spectator_sync(settings: &Settings, history: &MoveHistory, game: &GameState) -> Vec<Message> {
+    let mut messages = vec![sync_message(settings, history)];
+    if let Some(Outcome::Win(winner)) = game.outcome() {
+        if !game.board().bits().is_win(winner) {
+            messages.push(Message::Resigned {
+                side: winner.other(),
+            });
+        }
+    }
+    messages
+}
+
+/// Plays the moves on an empty board
+fn replay(settings: &Settings, moves: &[u32]) -> Result<(GameState, MoveHistory), NetError> {
+    let mut game = GameState::new(settings.new_board());
+    let mut history = MoveHistory::new();
+    for &column in moves {
+        history
+            .play(&mut game, column)
+            .map_err(NetError::IllegalMove)?;
+    }
+    Ok((game, history))
+}
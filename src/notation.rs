@@ -0,0 +1,569 @@
+//! Text notation for recorded games, modelled on chess PGN.
+//!
+//! A record starts with tag lines and continues with the moves, numbered
+//! in pairs like in chess, and ends with the result:
+//!
+//! ```text
+//! [Red "Alice"]
+//! [Blue "Bob"]
+//! [Size "7x6"]
+//! [WinLength "4"]
+//! [Date "2026.10.15"]
+//! [Result "1-0"]
+//!
+//! 1. 4 4 2. 5 3 3. 6 7 4. 3 1-0
+//! ```
+//!
+//! Columns are counted from 1 on the left. The result is `1-0` when red wins,
+//! `0-1` when blue wins, `1/2-1/2` for a draw and `*` for a game in progress.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::board::{ElemError, ElementType};
+use crate::game::{GameState, Outcome};
+use crate::history::MoveHistory;
+use crate::settings::Settings;
+
+/// A game as it is written down: who played, on which board and the moves
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRecord {
+    pub red: String,
+    pub blue: String,
+    pub settings: Settings,
+    /// `None` while the game is in progress
+    pub result: Option<Outcome>,
+    /// `YYYY.MM.DD`, `????.??.??` when unknown
+    pub date: String,
+    /// Columns counted from 0
+    pub moves: Vec<u32>,
+}
+
+/// Where and why a record could not be read, lines and columns start at 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A tag line is not `[Name "value"]`
+    MalformedTag,
+    UnknownTag(String),
+    InvalidSize(String),
+    InvalidWinLength(String),
+    InvalidResult(String),
+    /// The board from the tags cannot be played
+    InvalidSettings(String),
+    /// A word in the moves that is neither a column, a move number nor a result
+    UnexpectedToken(String),
+    /// The move cannot be played in the position reached so far
+    IllegalMove(ElemError),
+    /// Something follows the result, which has to come last
+    TrailingToken(String),
+    /// The result does not agree with the result tag or with the moves
+    ResultMismatch,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::MalformedTag => write!(f, "expected a tag like [Name \"value\"]"),
+            ParseErrorKind::UnknownTag(name) => write!(f, "unknown tag `{}`", name),
+            ParseErrorKind::InvalidSize(size) => {
+                write!(f, "invalid board size `{}`, expected WIDTHxHEIGHT", size)
+            }
+            ParseErrorKind::InvalidWinLength(value) => write!(f, "invalid win length `{}`", value),
+            ParseErrorKind::InvalidResult(value) => write!(f, "invalid result `{}`", value),
+            ParseErrorKind::InvalidSettings(reason) => write!(f, "{}", reason),
+            ParseErrorKind::UnexpectedToken(token) => write!(f, "unexpected `{}`", token),
+            ParseErrorKind::IllegalMove(err) => write!(f, "illegal move: {}", err),
+            ParseErrorKind::TrailingToken(token) => {
+                write!(f, "unexpected `{}` after the result", token)
+            }
+            ParseErrorKind::ResultMismatch => write!(f, "the result does not match the game"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> LoadError {
+        LoadError::Io(err)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(err: ParseError) -> LoadError {
+        LoadError::Parse(err)
+    }
+}
+
+impl GameRecord {
+    /// Writes down the moves on the board, dated today
+    pub fn new(settings: &Settings, history: &MoveHistory, game: &GameState) -> GameRecord {
+        GameRecord {
+            red: "Red".to_string(),
+            blue: "Blue".to_string(),
+            // The interface language is not part of the game
+            settings: Settings {
+                language: None,
+                ..settings.clone()
+            },
+            result: game.outcome(),
+            date: today(),
+            moves: history.moves().iter().map(|played| played.column).collect(),
+        }
+    }
+
+    /// Name of the player of the given colour
+    pub fn name_mut(&mut self, player: ElementType) -> &mut String {
+        match player {
+            ElementType::Red => &mut self.red,
+            ElementType::Blue => &mut self.blue,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<GameRecord, LoadError> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// Plays the moves on an empty board
+    pub fn replay(&self) -> Result<(GameState, MoveHistory), ElemError> {
+        self.replay_moves(self.moves.len())
+    }
+
+    /// Plays the first `count` moves on an empty board. After the last move
+    /// a win the moves do not show is taken as the loser's resignation.
+    pub fn replay_moves(&self, count: usize) -> Result<(GameState, MoveHistory), ElemError> {
+        let mut game = GameState::new(self.settings.new_board());
+        let mut history = MoveHistory::new();
+        for &column in self.moves.iter().take(count) {
+            history.play(&mut game, column)?;
+        }
+        if count >= self.moves.len() && !game.is_over() {
+            if let Some(Outcome::Win(winner)) = self.result {
+                game.resign(winner.other())?;
+            }
+        }
+        Ok((game, history))
+    }
+}
+
+impl std::str::FromStr for GameRecord {
+    type Err = ParseError;
+
+    /// Reads a record and checks that every move is legal
+    fn from_str(text: &str) -> Result<GameRecord, ParseError> {
+        Parser::new(text).record()
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Red \"{}\"]", escape(&self.red))?;
+        writeln!(f, "[Blue \"{}\"]", escape(&self.blue))?;
+        writeln!(
+            f,
+            "[Size \"{}x{}\"]",
+            self.settings.width, self.settings.height
+        )?;
+        writeln!(f, "[WinLength \"{}\"]", self.settings.win_length)?;
+        writeln!(f, "[Date \"{}\"]", escape(&self.date))?;
+        writeln!(f, "[Result \"{}\"]", result_token(self.result))?;
+        writeln!(f)?;
+        let mut line = String::new();
+        for (index, column) in self.moves.iter().enumerate() {
+            let mut token = String::new();
+            if index % 2 == 0 {
+                token += &format!("{}. ", index / 2 + 1);
+            }
+            token += &(column + 1).to_string();
+            push_wrapped(f, &mut line, &token)?;
+        }
+        push_wrapped(f, &mut line, result_token(self.result))?;
+        writeln!(f, "{}", line)
+    }
+}
+
+/// Adds the token to the line, starting a new one after about 80 characters
+fn push_wrapped(f: &mut fmt::Formatter<'_>, line: &mut String, token: &str) -> fmt::Result {
+    if !line.is_empty() && line.len() + token.len() >= 80 {
+        writeln!(f, "{}", line)?;
+        line.clear();
+    }
+    if !line.is_empty() {
+        line.push(' ');
+    }
+    line.push_str(token);
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn result_token(result: Option<Outcome>) -> &'static str {
+    match result {
+        Some(Outcome::Win(ElementType::Red)) => "1-0",
+        Some(Outcome::Win(ElementType::Blue)) => "0-1",
+        Some(Outcome::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+fn parse_result(token: &str) -> Option<Option<Outcome>> {
+    match token {
+        "1-0" => Some(Some(Outcome::Win(ElementType::Red))),
+        "0-1" => Some(Some(Outcome::Win(ElementType::Blue))),
+        "1/2-1/2" => Some(Some(Outcome::Draw)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+/// Today's date in UTC as `YYYY.MM.DD`
+fn today() -> String {
+    let Ok(since_epoch) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+    // Days to a civil date, from Howard Hinnant's `civil_from_days`
+    let days = (since_epoch.as_secs() / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// A word of the record and where it starts
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Parser<'a> {
+        Parser { text }
+    }
+
+    fn record(&self) -> Result<GameRecord, ParseError> {
+        let mut record = GameRecord {
+            red: "?".to_string(),
+            blue: "?".to_string(),
+            settings: Settings::default(),
+            result: None,
+            date: "????.??.??".to_string(),
+            moves: vec![],
+        };
+        let mut tag_result = None;
+        let mut settings_line = 1;
+        let mut tokens = vec![];
+
+        let mut in_tags = true;
+        for (index, line) in self.text.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim_start();
+            if in_tags && trimmed.starts_with('[') {
+                let column = line.len() - trimmed.len() + 1;
+                let error = |kind| ParseError {
+                    line: line_number,
+                    column,
+                    kind,
+                };
+                let (name, value) =
+                    parse_tag(trimmed.trim_end()).ok_or(error(ParseErrorKind::MalformedTag))?;
+                match name {
+                    "Red" => record.red = value,
+                    "Blue" => record.blue = value,
+                    "Date" => record.date = value,
+                    "Size" => {
+                        let (width, height) = value
+                            .split_once('x')
+                            .and_then(|(width, height)| {
+                                Some((width.parse().ok()?, height.parse().ok()?))
+                            })
+                            .ok_or_else(|| error(ParseErrorKind::InvalidSize(value.clone())))?;
+                        record.settings.width = width;
+                        record.settings.height = height;
+                        settings_line = line_number;
+                    }
+                    "WinLength" => {
+                        record.settings.win_length = value
+                            .parse()
+                            .map_err(|_| error(ParseErrorKind::InvalidWinLength(value.clone())))?;
+                        settings_line = line_number;
+                    }
+                    "Result" => {
+                        let result = parse_result(&value)
+                            .ok_or_else(|| error(ParseErrorKind::InvalidResult(value.clone())))?;
+                        tag_result = Some((result, line_number, column));
+                    }
+                    _ => return Err(error(ParseErrorKind::UnknownTag(name.to_string()))),
+                }
+                continue;
+            }
+            if !trimmed.is_empty() {
+                in_tags = false;
+            }
+            tokens.extend(words(line).map(|(column, text)| Token {
+                text,
+                line: line_number,
+                column,
+            }));
+        }
+
+        record.settings.validate().map_err(|err| ParseError {
+            line: settings_line,
+            column: 1,
+            kind: ParseErrorKind::InvalidSettings(err.to_string()),
+        })?;
+
+        let mut game = GameState::new(record.settings.new_board());
+        let mut result = None;
+        for token in tokens {
+            let error = |kind| ParseError {
+                line: token.line,
+                column: token.column,
+                kind,
+            };
+            if result.is_some() {
+                return Err(error(ParseErrorKind::TrailingToken(token.text.to_string())));
+            }
+            if let Some(parsed) = parse_result(token.text) {
+                // A finished game has to end the way the moves say,
+                // a game in progress may still end by resignation
+                if game.is_over() && parsed != game.outcome() {
+                    return Err(error(ParseErrorKind::ResultMismatch));
+                }
+                result = Some(parsed);
+                continue;
+            }
+            if let Some(number) = token.text.strip_suffix('.') {
+                if !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()) {
+                    continue;
+                }
+            }
+            let column = match token.text.parse::<u32>() {
+                Ok(column) if column >= 1 => column - 1,
+                _ => {
+                    return Err(error(ParseErrorKind::UnexpectedToken(
+                        token.text.to_string(),
+                    )))
+                }
+            };
+            game.play(column)
+                .map_err(|err| error(ParseErrorKind::IllegalMove(err)))?;
+            record.moves.push(column);
+        }
+
+        record.result = match (result, tag_result) {
+            (Some(result), Some((tagged, line, column))) if result != tagged => {
+                return Err(ParseError {
+                    line,
+                    column,
+                    kind: ParseErrorKind::ResultMismatch,
+                })
+            }
+            (Some(result), _) => result,
+            (None, Some((tagged, line, column))) => {
+                if game.is_over() && tagged != game.outcome() {
+                    return Err(ParseError {
+                        line,
+                        column,
+                        kind: ParseErrorKind::ResultMismatch,
+                    });
+                }
+                tagged
+            }
+            (None, None) => game.outcome(),
+        };
+        Ok(record)
+    }
+}
+
+/// Splits a `[Name "value"]` line into the name and the unescaped value
+fn parse_tag(line: &str) -> Option<(&str, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, rest) = inner.split_once(char::is_whitespace)?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let quoted = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '"' => return None,
+            c => value.push(c),
+        }
+    }
+    Some((name, value))
+}
+
+/// Words of the line together with their column, counted in characters from 1
+fn words(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = None;
+    let mut words = vec![];
+    let mut column = 0;
+    for (offset, c) in line.char_indices() {
+        column += 1;
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((offset, column)),
+            (true, Some((begin, begin_column))) => {
+                words.push((begin_column, &line[begin..offset]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((begin, begin_column)) = start {
+        words.push((begin_column, &line[begin..]));
+    }
+    words.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> ParseError {
+        text.parse::<GameRecord>().unwrap_err()
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let settings = Settings::default();
+        let mut game = GameState::new(settings.new_board());
+        let mut history = MoveHistory::new();
+        // Long enough for the moves to take several lines
+        for column in (0..).map(|i| i * 3 % 7).take(30) {
+            if history.play(&mut game, column).is_err() {
+                break;
+            }
+        }
+        let mut record = GameRecord::new(&settings, &history, &game);
+        record.red = "Ann \"the \\ red\"".to_string();
+        record.blue = "Bob".to_string();
+        let text = record.to_string();
+        assert!(text.lines().count() > 8);
+        assert_eq!(text.parse(), Ok(record));
+    }
+
+    #[test]
+    fn a_malformed_tag_is_reported_where_it_starts() {
+        assert_eq!(
+            parse_error("[Red \"Alice\"]\n  [Blue Bob]\n"),
+            ParseError {
+                line: 2,
+                column: 3,
+                kind: ParseErrorKind::MalformedTag,
+            }
+        );
+    }
+
+    #[test]
+    fn an_unknown_token_is_reported() {
+        assert_eq!(
+            parse_error("[Size \"7x6\"]\n\n1. 4 x 2. 5 *"),
+            ParseError {
+                line: 3,
+                column: 6,
+                kind: ParseErrorKind::UnexpectedToken("x".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn a_move_into_a_full_column_is_reported() {
+        assert_eq!(
+            parse_error("[Size \"4x2\"]\n[WinLength \"3\"]\n\n1. 1 1 2. 1"),
+            ParseError {
+                line: 4,
+                column: 11,
+                kind: ParseErrorKind::IllegalMove(ElemError::ColumnFull),
+            }
+        );
+    }
+
+    #[test]
+    fn a_result_against_the_moves_is_reported() {
+        // Red has four in the first column
+        assert_eq!(
+            parse_error("1. 1 2 2. 1 2 3. 1 2 4. 1 0-1"),
+            ParseError {
+                line: 1,
+                column: 27,
+                kind: ParseErrorKind::ResultMismatch,
+            }
+        );
+        // The tag and the moves disagree about a resignation
+        assert_eq!(
+            parse_error("[Result \"1-0\"]\n\n1. 4 0-1"),
+            ParseError {
+                line: 1,
+                column: 1,
+                kind: ParseErrorKind::ResultMismatch,
+            }
+        );
+    }
+
+    #[test]
+    fn a_resignation_is_replayed() {
+        let record: GameRecord = "[Result \"0-1\"]\n\n1. 4 4 2. 5 0-1".parse().unwrap();
+        let (game, history) = record.replay().unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(game.outcome(), Some(Outcome::Win(ElementType::Blue)));
+        // Before the last move the game was still going on
+        let (game, _) = record.replay_moves(2).unwrap();
+        assert_eq!(game.outcome(), None);
+    }
+}
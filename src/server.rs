@@ -0,0 +1,306 @@
+//! Dedicated server that hosts many network games at once.
+//!
+//! The server speaks the protocol of `net` as the host of every game, so
+//! players connect to it the same way they join another player. The first
+//! client without a game waits for the next one, the first of the two plays
+//! red. Each game runs in its own thread with the authoritative board.
+//!
+//! The server has no spectators: a client that asks to watch, or answers the
+//! greeting in another version, is disconnected before it is paired.
+//!
+//! Every move is checked against the rules before the other player gets it:
+//! a move out of turn, into a full column or after the end of the game is
+//! not passed on, the sender gets `sync` with the real game instead.
+
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::board::ElementType;
+use crate::game::GameState;
+use crate::history::MoveHistory;
+use crate::net::{read_messages, write_message, Message, NetError, GREETING_TIMEOUT, VERSION};
+use crate::settings::Settings;
+
+/// Longest line accepted as the answer to the greeting
+const MAX_GREETING: usize = 64;
+
+pub struct Server {
+    listener: TcpListener,
+    settings: Settings,
+}
+
+/// What the lobby learns about the clients
+enum Arrival {
+    Connected(TcpStream),
+    /// The client greeted as this side has answered, `agreed` when it plays
+    Greeted {
+        side: ElementType,
+        stream: TcpStream,
+        agreed: bool,
+    },
+}
+
+/// Clients that do not play yet
+struct Lobby {
+    settings: Settings,
+    /// Players who agreed to their side, by `ElementType::index`
+    waiting: [VecDeque<TcpStream>; 2],
+    /// Number of clients greeted as each side that have not answered yet
+    greeting: [usize; 2],
+}
+
+/// One game on the server
+struct HostedGame {
+    settings: Settings,
+    game: GameState,
+    history: MoveHistory,
+    /// Connections of the red and blue player, by `ElementType::index`
+    players: [TcpStream; 2],
+    /// Which players have asked for a rematch
+    rematch: [bool; 2],
+}
+
+impl Server {
+    /// Every game on the server is played with these settings
+    pub fn bind(addr: impl ToSocketAddrs, settings: &Settings) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            settings: Settings {
+                language: None,
+                ..settings.clone()
+            },
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Pairs the connecting clients and starts a game for every pair.
+    /// Runs as long as the process does.
+    pub fn run(&self) {
+        let (sender, arrivals) = mpsc::channel();
+        let lobby = Lobby {
+            settings: self.settings.clone(),
+            waiting: Default::default(),
+            greeting: [0; 2],
+        };
+        let lobby_sender = sender.clone();
+        thread::spawn(move || lobby.run(lobby_sender, arrivals));
+        for stream in self.listener.incoming() {
+            // A client that failed to connect does not stop the others
+            let Ok(stream) = stream else {
+                continue;
+            };
+            if sender.send(Arrival::Connected(stream)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Lobby {
+    /// Greets every client in its own thread and starts a game as soon as
+    /// a red and a blue player wait
+    fn run(mut self, sender: Sender<Arrival>, arrivals: Receiver<Arrival>) {
+        for arrival in arrivals {
+            for waiting in &mut self.waiting {
+                waiting.retain(is_open);
+            }
+            match arrival {
+                Arrival::Connected(stream) => {
+                    let side = self.next_side();
+                    self.greeting[side.index() as usize] += 1;
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        let agreed = greet(&stream, side);
+                        let _ = sender.send(Arrival::Greeted {
+                            side,
+                            stream,
+                            agreed,
+                        });
+                    });
+                }
+                Arrival::Greeted {
+                    side,
+                    stream,
+                    agreed,
+                } => {
+                    self.greeting[side.index() as usize] -= 1;
+                    // A refused client is disconnected only once it is no longer
+                    // counted, whoever it makes room for gets the right side
+                    if agreed {
+                        self.admit(side, stream);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts a game with the first opponent waiting, or lets the player wait
+    fn admit(&mut self, side: ElementType, stream: TcpStream) {
+        let Some(opponent) = self.waiting[side.other().index() as usize].pop_front() else {
+            self.waiting[side.index() as usize].push_back(stream);
+            return;
+        };
+        let (red, blue) = match side {
+            ElementType::Red => (stream, opponent),
+            ElementType::Blue => (opponent, stream),
+        };
+        let settings = self.settings.clone();
+        thread::spawn(move || host_game(settings, red, blue));
+    }
+
+    /// The side with fewer players, so that every waiting player gets an
+    /// opponent. Red when both have as many, the first of two plays red.
+    fn next_side(&self) -> ElementType {
+        let players = |side: ElementType| {
+            self.waiting[side.index() as usize].len() + self.greeting[side.index() as usize]
+        };
+        if players(ElementType::Red) <= players(ElementType::Blue) {
+            ElementType::Red
+        } else {
+            ElementType::Blue
+        }
+    }
+}
+
+/// Tells the client its side and waits for the client to agree.
+/// The server speaks to each player as its opponent.
+fn greet(stream: &TcpStream, side: ElementType) -> bool {
+    let hello = Message::Hello {
+        version: VERSION,
+        side: side.other(),
+    };
+    let answer = write_message(stream, &hello)
+        .and_then(|()| stream.set_read_timeout(Some(GREETING_TIMEOUT)))
+        .and_then(|()| read_line(stream))
+        .map(|line| line.parse());
+    // A spectator, another version or not a client at all is refused
+    matches!(
+        answer,
+        Ok(Ok(Message::Hello { version, side: answer })) if version == VERSION && answer == side
+    ) && stream.set_read_timeout(None).is_ok()
+}
+
+/// Reads one line byte by byte, so that the rest of the stream is left to
+/// the game
+fn read_line(mut stream: &TcpStream) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while line.len() <= MAX_GREETING {
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if byte[0] == b'\n' {
+            return String::from_utf8(line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+        line.push(byte[0]);
+    }
+    Err(io::ErrorKind::InvalidData.into())
+}
+
+/// Whether a waiting client is still there, it may leave before its game starts
+fn is_open(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let open = match stream.peek(&mut [0]) {
+        Ok(read) => read > 0,
+        Err(err) => err.kind() == io::ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_ok() && open
+}
+
+/// Plays one game until either player leaves
+fn host_game(settings: Settings, red: TcpStream, blue: TcpStream) {
+    let (sender, inputs) = mpsc::channel();
+    for (side, stream) in [(ElementType::Red, &red), (ElementType::Blue, &blue)] {
+        let sender = sender.clone();
+        if read_messages(stream, move |message| sender.send((side, message)).is_ok()).is_err() {
+            return;
+        }
+    }
+    let mut hosted = HostedGame {
+        game: GameState::new(settings.new_board()),
+        settings,
+        history: MoveHistory::new(),
+        players: [red, blue],
+        rematch: [false; 2],
+    };
+    let _ = hosted.play(inputs);
+    for player in &hosted.players {
+        let _ = player.shutdown(Shutdown::Both);
+    }
+}
+
+impl HostedGame {
+    fn play(
+        &mut self,
+        inputs: Receiver<(ElementType, Result<Message, NetError>)>,
+    ) -> Result<(), NetError> {
+        for side in [ElementType::Red, ElementType::Blue] {
+            self.sync(side)?;
+        }
+        loop {
+            let (side, message) = inputs.recv().map_err(|_| NetError::Closed)?;
+            self.receive(side, message?)?;
+        }
+    }
+
+    fn send(&self, side: ElementType, message: &Message) -> Result<(), NetError> {
+        write_message(&self.players[side.index() as usize], message)?;
+        Ok(())
+    }
+
+    /// Sends the whole game to the player
+    fn sync(&self, side: ElementType) -> Result<(), NetError> {
+        let moves = self
+            .history
+            .moves()
+            .iter()
+            .map(|played| played.column)
+            .collect();
+        self.send(
+            side,
+            &Message::Sync {
+                settings: self.settings.clone(),
+                moves,
+            },
+        )
+    }
+
+    /// Applies a message of the player, the other player gets it only when
+    /// the rules allow it
+    fn receive(&mut self, side: ElementType, message: Message) -> Result<(), NetError> {
+        let allowed = match message {
+            Message::Move { column } => {
+                self.game.turn() == side && self.history.play(&mut self.game, column).is_ok()
+            }
+            Message::Resign => self.game.resign(side).is_ok(),
+            Message::Rematch => {
+                self.rematch[side.index() as usize] = true;
+                if self.rematch == [true; 2] {
+                    self.rematch = [false; 2];
+                    self.game.reset();
+                    self.history.clear();
+                }
+                true
+            }
+            Message::Hello { .. }
+            | Message::Sync { .. }
+            | Message::Watch { .. }
+            | Message::Resigned { .. }
+            | Message::Spectators { .. } => false,
+        };
+        if allowed {
+            self.send(side.other(), &message)
+        } else {
+            self.sync(side)
+        }
+    }
+}
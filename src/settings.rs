@@ -0,0 +1,181 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::bitboard::{BitBoard, MAX_WIN_LENGTH};
+use crate::board::Board;
+
+/// Board size and rules of a game, and the language of the messages.
+///
+/// Read from a text file with one `key = value` pair per line,
+/// lines starting with `#` are comments:
+///
+/// ```text
+/// # Connect 5 on a larger board
+/// width = 9
+/// height = 7
+/// win_length = 5
+/// language = en
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub width: u32,
+    pub height: u32,
+    /// Number of pieces in a row needed to win
+    pub win_length: u32,
+    /// Catalog of the messages, the system language when `None`
+    pub language: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    /// The line is not a `key = value` pair
+    Syntax {
+        line: usize,
+    },
+    UnknownKey {
+        line: usize,
+        key: String,
+    },
+    InvalidValue {
+        line: usize,
+        key: String,
+    },
+    /// The values are fine on their own but cannot be played together
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "{}", err),
+            SettingsError::Syntax { line } => write!(f, "line {}: expected `key = value`", line),
+            SettingsError::UnknownKey { line, key } => {
+                write!(f, "line {}: unknown setting `{}`", line, key)
+            }
+            SettingsError::InvalidValue { line, key } => {
+                write!(f, "line {}: invalid value of `{}`", line, key)
+            }
+            SettingsError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(err: io::Error) -> SettingsError {
+        SettingsError::Io(err)
+    }
+}
+
+impl Default for Settings {
+    /// The classic 7x6 board with four in a row
+    fn default() -> Settings {
+        Settings {
+            width: 7,
+            height: 6,
+            win_length: 4,
+            language: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, keys missing from the file keep their defaults
+    pub fn load(path: impl AsRef<Path>) -> Result<Settings, SettingsError> {
+        Settings::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Settings, SettingsError> {
+        let mut settings = Settings::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(SettingsError::Syntax { line: line_number })?;
+            let key = key.trim();
+            if key == "language" {
+                settings.language = Some(value.trim().to_string());
+                continue;
+            }
+            let field = match key {
+                "width" => &mut settings.width,
+                "height" => &mut settings.height,
+                "win_length" => &mut settings.win_length,
+                _ => {
+                    return Err(SettingsError::UnknownKey {
+                        line: line_number,
+                        key: key.to_string(),
+                    })
+                }
+            };
+            *field = value
+                .trim()
+                .parse()
+                .map_err(|_| SettingsError::InvalidValue {
+                    line: line_number,
+                    key: key.to_string(),
+                })?;
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Checks that a game can be played with these settings
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !BitBoard::fits(self.width, self.height) {
+            return Err(SettingsError::Invalid(format!(
+                "a {}x{} board is not supported",
+                self.width, self.height
+            )));
+        }
+        if self.win_length < 2
+            || self.win_length > MAX_WIN_LENGTH
+            || self.win_length > self.width.max(self.height)
+        {
+            return Err(SettingsError::Invalid(format!(
+                "a line of {} cannot be played on a {}x{} board",
+                self.win_length, self.width, self.height
+            )));
+        }
+        Ok(())
+    }
+
+    /// An empty board of this size and rules
+    pub fn new_board(&self) -> Board {
+        Board::with_win_length(self.width, self.height, self.win_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boards_too_large_for_a_bitboard_are_invalid() {
+        let text = "width = 65536\nheight = 65535\n";
+        assert!(matches!(
+            Settings::parse(text),
+            Err(SettingsError::Invalid(_))
+        ));
+        let text = "width = 4294967295\nheight = 4294967295\n";
+        assert!(matches!(
+            Settings::parse(text),
+            Err(SettingsError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn the_largest_boards_are_valid() {
+        let settings = Settings::parse("width = 16\nheight = 7\n").unwrap();
+        assert_eq!((settings.width, settings.height), (16, 7));
+        assert!(Settings::parse("width = 17\nheight = 7\n").is_err());
+    }
+}
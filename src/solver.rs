@@ -0,0 +1,437 @@
+//! Perfect play for small boards.
+//!
+//! A negamax search with alpha-beta pruning, narrowed with null windows around
+//! the expected score. Scores follow the usual convention: a positive score
+//! means the player to move wins, and the sooner the win the higher the score.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::bitboard::{centre_out, BitBoard, Mask};
+use crate::game::{GameState, Outcome};
+
+/// Number of entries in the default transposition table, a prime for better spreading
+const TABLE_SIZE: usize = (1 << 20) + 7;
+
+/// Exact value of a position for the player to move
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Solution {
+    /// The player to move wins, the game lasts `plies` more moves of both players
+    Win {
+        plies: u32,
+    },
+    /// The opponent wins after `plies` more moves of both players
+    Loss {
+        plies: u32,
+    },
+    Draw,
+}
+
+impl Solution {
+    /// The same result seen by the other player one move earlier
+    fn before_move(self) -> Solution {
+        match self {
+            Solution::Win { plies } => Solution::Loss { plies: plies + 1 },
+            Solution::Loss { plies } => Solution::Win { plies: plies + 1 },
+            Solution::Draw => Solution::Draw,
+        }
+    }
+}
+
+/// Solves the position for the player to move, `None` once the game is over
+pub fn solve(game: &GameState) -> Option<Solution> {
+    Solver::new().solve(game)
+}
+
+/// Result of every move of the player to move, `None` for full columns
+pub fn analyze(game: &GameState) -> Vec<Option<Solution>> {
+    Solver::new().analyze(game)
+}
+
+#[derive(Copy, Clone, Default)]
+struct Entry {
+    /// Key of the position, 0 marks an empty entry as no position has it
+    key: Mask,
+    /// Upper bound of the score
+    bound: i8,
+}
+
+/// Stores upper bounds of already searched positions, newer entries replace older ones
+struct TranspositionTable {
+    entries: Vec<Entry>,
+}
+
+impl TranspositionTable {
+    fn new(size: usize) -> TranspositionTable {
+        TranspositionTable {
+            entries: vec![Entry::default(); size],
+        }
+    }
+
+    fn index(&self, key: Mask) -> usize {
+        (key % self.entries.len() as Mask) as usize
+    }
+
+    fn get(&self, key: Mask) -> Option<i8> {
+        let entry = self.entries[self.index(key)];
+        (entry.key == key).then_some(entry.bound)
+    }
+
+    fn put(&mut self, key: Mask, bound: i8) {
+        let index = self.index(key);
+        self.entries[index] = Entry { key, bound };
+    }
+}
+
+/// A position seen from the player to move
+#[derive(Copy, Clone)]
+struct Position {
+    /// Pieces of the player to move
+    current: Mask,
+    /// Pieces of both players
+    occupied: Mask,
+    moves: u32,
+}
+
+impl Position {
+    fn key(&self, board: &BitBoard) -> Mask {
+        self.current + self.occupied + board.bottom_mask()
+    }
+
+    /// Plays the move given as the bit of the cell and passes the turn
+    fn play(&mut self, cell: Mask) {
+        self.current ^= self.occupied;
+        self.occupied |= cell;
+        self.moves += 1;
+    }
+
+    /// The lowest empty cell of every column that is not full
+    fn possible(&self, board: &BitBoard) -> Mask {
+        (self.occupied + board.bottom_mask()) & board.board_mask()
+    }
+
+    fn winning_cells(&self, board: &BitBoard) -> Mask {
+        board.winning_cells(self.current, self.occupied)
+    }
+
+    fn opponent_winning_cells(&self, board: &BitBoard) -> Mask {
+        board.winning_cells(self.current ^ self.occupied, self.occupied)
+    }
+
+    fn can_win_next(&self, board: &BitBoard) -> bool {
+        self.winning_cells(board) & self.possible(board) != 0
+    }
+
+    /// Moves that do not let the opponent win right away.
+    /// Empty when every move loses, which only happens with two threats to block.
+    fn non_losing_moves(&self, board: &BitBoard) -> Mask {
+        let mut possible = self.possible(board);
+        let opponent_wins = self.opponent_winning_cells(board);
+        let forced = possible & opponent_wins;
+        if forced != 0 {
+            if forced & (forced - 1) != 0 {
+                return 0;
+            }
+            possible = forced;
+        }
+        // Never play right under a cell where the opponent would win
+        possible & !(opponent_wins >> 1)
+    }
+
+    /// Number of lines the move would threaten, used to try the strongest moves first
+    fn move_score(&self, board: &BitBoard, cell: Mask) -> u32 {
+        board
+            .winning_cells(self.current | cell, self.occupied | cell)
+            .count_ones()
+    }
+}
+
+/// Solves positions of one board size, reusing the transposition table between calls
+pub struct Solver {
+    table: TranspositionTable,
+    /// Empty board of the size being solved, used for its bit layout
+    layout: BitBoard,
+    stop: Option<Arc<AtomicBool>>,
+    stopped: bool,
+    nodes: u64,
+}
+
+impl Default for Solver {
+    fn default() -> Solver {
+        Solver::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::with_table_size(TABLE_SIZE)
+    }
+
+    pub fn with_table_size(size: usize) -> Solver {
+        Solver {
+            table: TranspositionTable::new(size.max(1)),
+            layout: BitBoard::new(7, 6),
+            stop: None,
+            stopped: false,
+            nodes: 0,
+        }
+    }
+
+    /// The search gives up and returns `None` once the flag is set
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Solver {
+        self.set_stop(stop);
+        self
+    }
+
+    /// Replaces the flag of `with_stop`, for a solver kept between searches
+    pub fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = Some(stop);
+    }
+
+    /// Number of positions searched so far
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Solves the position for the player to move, `None` once the game is over
+    /// or when the search was stopped
+    pub fn solve(&mut self, game: &GameState) -> Option<Solution> {
+        if game.is_over() {
+            return None;
+        }
+        let bits = game.board().bits();
+        self.prepare(bits);
+        let position = Position {
+            current: bits.mask(game.turn()),
+            occupied: bits.occupied(),
+            moves: bits.moves(),
+        };
+        let score = self.solve_position(&position)?;
+        Some(self.solution(score, position.moves))
+    }
+
+    /// Result of every move of the player to move, `None` for full columns
+    pub fn analyze(&mut self, game: &GameState) -> Vec<Option<Solution>> {
+        (0..game.board().width())
+            .map(|column| {
+                let mut child = game.clone();
+                child.play(column).ok()?;
+                match child.outcome() {
+                    Some(Outcome::Win(_)) => Some(Solution::Win { plies: 1 }),
+                    Some(Outcome::Draw) => Some(Solution::Draw),
+                    None => self.solve(&child).map(Solution::before_move),
+                }
+            })
+            .collect()
+    }
+
+    /// Starts over with an empty table when the board size or the rules change
+    fn prepare(&mut self, bits: &BitBoard) {
+        self.stopped = false;
+        if self.layout.width() != bits.width()
+            || self.layout.height() != bits.height()
+            || self.layout.win_length() != bits.win_length()
+        {
+            self.layout = BitBoard::with_win_length(bits.width(), bits.height(), bits.win_length());
+            self.table = TranspositionTable::new(self.table.entries.len());
+        }
+    }
+
+    fn size(&self) -> i32 {
+        (self.layout.width() * self.layout.height()) as i32
+    }
+
+    /// Finds the exact score by repeating null window searches.
+    /// The first windows are close to the shortest wins and losses, which are
+    /// cheap to prove, and then the window moves toward a draw.
+    fn solve_position(&mut self, position: &Position) -> Option<i32> {
+        let moves = position.moves as i32;
+        if position.can_win_next(&self.layout) {
+            return Some((self.size() + 1 - moves) / 2);
+        }
+        let mut min = -(self.size() - moves) / 2;
+        let mut max = (self.size() + 1 - moves) / 2;
+        while min < max {
+            let mut med = min + (max - min) / 2;
+            if med <= 0 && min / 2 < med {
+                med = min / 2;
+            } else if med >= 0 && max / 2 > med {
+                med = max / 2;
+            }
+            let score = self.negamax(position, med, med + 1);
+            if self.stopped {
+                return None;
+            }
+            if score <= med {
+                max = score;
+            } else {
+                min = score;
+            }
+        }
+        Some(min)
+    }
+
+    /// Alpha-beta search of a position where the player to move cannot win right away
+    fn negamax(&mut self, position: &Position, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.nodes % 4096 == 0 {
+            self.stopped |= self
+                .stop
+                .as_ref()
+                .is_some_and(|stop| stop.load(Ordering::Relaxed));
+        }
+        if self.stopped {
+            return alpha;
+        }
+
+        let moves = position.moves as i32;
+        let next = position.non_losing_moves(&self.layout);
+        if next == 0 {
+            // Whatever we play, the opponent wins with the next move
+            return -(self.size() - moves) / 2;
+        }
+        if moves >= self.size() - 2 {
+            return 0;
+        }
+
+        let min = -(self.size() - 2 - moves) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        let key = position.key(&self.layout);
+        let mut max = (self.size() - 1 - moves) / 2;
+        if let Some(bound) = self.table.get(key) {
+            max = bound as i32;
+        }
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        for cell in self.ordered_moves(position, next) {
+            let mut child = *position;
+            child.play(cell);
+            let score = -self.negamax(&child, -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        if !self.stopped {
+            self.table.put(key, alpha as i8);
+        }
+        alpha
+    }
+
+    /// Moves from the centre outwards, the ones creating more threats first
+    fn ordered_moves(&self, position: &Position, next: Mask) -> Vec<Mask> {
+        let mut moves: Vec<(u32, Mask)> = centre_out(self.layout.width())
+            .map(|column| next & self.layout.column_mask(column))
+            .filter(|&cell| cell != 0)
+            .map(|cell| (position.move_score(&self.layout, cell), cell))
+            .collect();
+        // The sort is stable, so equal moves keep the centre first order
+        moves.sort_by(|a, b| b.0.cmp(&a.0));
+        moves.into_iter().map(|(_, cell)| cell).collect()
+    }
+
+    /// Turns a score into the result and the number of plies until the end
+    fn solution(&self, score: i32, moves: u32) -> Solution {
+        if score == 0 {
+            return Solution::Draw;
+        }
+        // The winner makes the last move when `last` pieces are on the board,
+        // the player to move wins on plies of the same parity as `moves`
+        let winner_parity = if score > 0 {
+            moves % 2
+        } else {
+            (moves + 1) % 2
+        };
+        let last = (self.size() + 1 - 2 * score.abs()) as u32;
+        let last = if last % 2 == winner_parity {
+            last
+        } else {
+            last - 1
+        };
+        let plies = last - moves + 1;
+        if score > 0 {
+            Solution::Win { plies }
+        } else {
+            Solution::Loss { plies }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    /// The game after the columns were played on the board
+    fn position(board: Board, columns: &[u32]) -> GameState {
+        let mut game = GameState::new(board);
+        for &column in columns {
+            game.play(column).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn a_win_in_one_move() {
+        let game = position(Board::default(), &[0, 1, 0, 1, 0, 1]);
+        assert_eq!(solve(&game), Some(Solution::Win { plies: 1 }));
+    }
+
+    #[test]
+    fn two_threats_win_and_lose_by_parity() {
+        // Red sets up three in a row with both ends open
+        let game = position(Board::default(), &[1, 1, 2, 2]);
+        assert_eq!(solve(&game), Some(Solution::Win { plies: 3 }));
+        // Blue can block only one end
+        let game = position(Board::default(), &[1, 1, 2, 2, 3]);
+        assert_eq!(solve(&game), Some(Solution::Loss { plies: 2 }));
+    }
+
+    #[test]
+    fn a_full_board_endgame_is_drawn() {
+        let columns = [
+            3, 3, 1, 6, 5, 0, 1, 1, 4, 2, 6, 6, 1, 4, 1, 2, 3, 1, 4, 3, 4, 4, 5, 2, 3, 6, 3, 0, 6,
+            4, 2, 6, 0, 5, 5, 5, 5, 2,
+        ];
+        let game = position(Board::default(), &columns);
+        assert_eq!(solve(&game), Some(Solution::Draw));
+        let mut game = game;
+        for column in [0, 2, 0, 0] {
+            game.play(column).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+        assert_eq!(solve(&game), None);
+    }
+
+    #[test]
+    fn full_columns_have_no_result() {
+        let board = Board::with_win_length(4, 4, 3);
+        let game = position(board, &[0, 0, 0, 0, 1, 2, 1, 2]);
+        let results = Solver::new().analyze(&game);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], None);
+        assert_eq!(results[1], Some(Solution::Win { plies: 1 }));
+        assert!(results[2..].iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn a_stopped_search_gives_up() {
+        let stop = Arc::new(AtomicBool::new(true));
+        let mut solver = Solver::new().with_stop(stop);
+        assert_eq!(solver.solve(&GameState::default()), None);
+        assert!(solver.nodes() > 0);
+    }
+}
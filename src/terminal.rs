@@ -0,0 +1,183 @@
+//! Text front-end for machines without a display.
+//!
+//! The board is printed after every move and columns are read from stdin one
+//! per line, so a game can also be scripted by piping the moves in.
+//! `save <file>` and `load <file>` store and restore the game.
+
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use connect_four::{
+    ai, i18n::Catalog, notation::GameRecord, ElementType, GameState, MoveHistory, Outcome, Pos,
+    Settings,
+};
+
+use crate::cli::{Options, Start};
+
+pub fn run(mut settings: Settings, start: Start, options: &Options, catalog: &Catalog) -> ExitCode {
+    let replayed = match start {
+        Start::NewGame => Ok((GameState::new(settings.new_board()), MoveHistory::new())),
+        Start::Continue(record) => record.replay(),
+        Start::Replay(_) => {
+            println!("{}", catalog.get("terminal.no_replay"));
+            return ExitCode::FAILURE;
+        }
+        Start::Host(_) | Start::Join(_) | Start::Watch(_) => {
+            println!("{}", catalog.get("terminal.no_network"));
+            return ExitCode::FAILURE;
+        }
+    };
+    let (mut game, mut history) = match replayed {
+        Ok(replayed) => replayed,
+        Err(err) => {
+            println!(
+                "{}",
+                catalog.format("record.replay_failed", &[("error", &catalog.error(&err))])
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let config = options.ai_config();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    print!("{}", render(&game));
+    while !game.is_over() {
+        let column = if options.ai == Some(game.turn()) {
+            let Some(column) = ai::best_move(&game, &config) else {
+                break;
+            };
+            println!(
+                "{}",
+                catalog.format("terminal.computer_move", &[("column", &(column + 1))])
+            );
+            column
+        } else {
+            let player = catalog.player(game.turn());
+            let width = game.board().width();
+            print!(
+                "{} ",
+                catalog.format("terminal.prompt", &[("player", &player), ("width", &width)])
+            );
+            let _ = io::stdout().flush();
+            let Some(Ok(line)) = lines.next() else {
+                println!();
+                return ExitCode::SUCCESS;
+            };
+            let line = line.trim();
+            if line == "q" {
+                return ExitCode::SUCCESS;
+            }
+            if let Some(path) = line.strip_prefix("save ") {
+                let mut record = GameRecord::new(&settings, &history, &game);
+                if let Some(side) = options.ai {
+                    *record.name_mut(side) = "Computer".to_string();
+                }
+                match record.save(path.trim()) {
+                    Ok(()) => println!(
+                        "{}",
+                        catalog.format("record.saved", &[("path", &path.trim())])
+                    ),
+                    Err(err) => println!(
+                        "{}",
+                        catalog.format("record.save_failed", &[("error", &catalog.error(&err))])
+                    ),
+                }
+                continue;
+            }
+            if let Some(path) = line.strip_prefix("load ") {
+                match GameRecord::load(path.trim()) {
+                    Ok(record) => match record.replay() {
+                        Ok(replayed) => {
+                            settings = record.settings.clone();
+                            (game, history) = replayed;
+                            print!("{}", render(&game));
+                        }
+                        Err(err) => println!(
+                            "{}",
+                            catalog
+                                .format("record.replay_failed", &[("error", &catalog.error(&err))])
+                        ),
+                    },
+                    Err(err) => println!(
+                        "{}",
+                        catalog.format("record.load_failed", &[("error", &catalog.error(&err))])
+                    ),
+                }
+                continue;
+            }
+            match line.parse::<u32>() {
+                Ok(column) if column >= 1 => column - 1,
+                _ => {
+                    println!("{}", catalog.get("terminal.enter_column"));
+                    continue;
+                }
+            }
+        };
+        if let Err(err) = history.play(&mut game, column) {
+            println!(
+                "{}",
+                catalog.format("move.invalid", &[("error", &catalog.error(&err))])
+            );
+            continue;
+        }
+        print!("{}", render(&game));
+    }
+
+    match game.outcome() {
+        Some(Outcome::Win(player)) => println!(
+            "{}",
+            catalog.format("game_over.win", &[("player", &catalog.player(player))])
+        ),
+        Some(Outcome::Draw) => println!("{}", catalog.get("game_over.draw")),
+        None => {}
+    }
+    ExitCode::SUCCESS
+}
+
+/// The board as text, top row first, with column numbers above it
+pub fn render(game: &GameState) -> String {
+    let board = game.board();
+    let mut text = String::from(" ");
+    for x in 0..board.width() {
+        text += &format!("{:>2}", (x + 1) % 100);
+    }
+    text += "\n";
+    for y in (0..board.height()).rev() {
+        text += "|";
+        for x in 0..board.width() {
+            text.push(' ');
+            text.push(match board.get(&Pos::new(x, y)) {
+                Ok(ElementType::Red) => 'R',
+                Ok(ElementType::Blue) => 'B',
+                Err(_) => '.',
+            });
+        }
+        text += " |\n";
+    }
+    text += &format!("+{}+\n", "-".repeat(board.width() as usize * 2 + 1));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use connect_four::Board;
+
+    use super::*;
+
+    #[test]
+    fn render_draws_the_top_row_first() {
+        let mut game = GameState::new(Board::with_win_length(4, 3, 3));
+        for column in [0, 1, 0] {
+            game.play(column).unwrap();
+        }
+        assert_eq!(
+            render(&game),
+            "  1 2 3 4\n\
+             | . . . . |\n\
+             | R . . . |\n\
+             | R B . . |\n\
+             +---------+\n"
+        );
+    }
+}
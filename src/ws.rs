@@ -0,0 +1,453 @@
+//! WebSocket endpoint speaking JSON, for clients that do not link this crate.
+//!
+//! Every message is a text frame with one JSON object whose `type` field
+//! names it. The schema is described in `docs/websocket.md`, its version is
+//! `SCHEMA_VERSION` and it is sent with every message of the server.
+//!
+//! A client creates a game and another one joins it by its number. Like the
+//! native server, this one keeps the authoritative board of every game: a
+//! move is checked against the rules and the new `state` goes to both
+//! players, a refused one gets `error` back.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tungstenite::{Error as WsError, Message as Frame};
+
+use crate::board::{Board, ElemError, ElementType, Pos};
+use crate::game::{GameState, Outcome};
+use crate::history::MoveHistory;
+use crate::settings::Settings;
+
+/// Changes whenever a message changes in a way old clients do not understand
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How long a connection waits for its client before it sends what the
+/// other player did
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A side of the game as it is written in JSON
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Player {
+    Red,
+    Blue,
+}
+
+/// The board as the clients see it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BoardState {
+    pub width: u32,
+    pub height: u32,
+    /// Owners of the cells, `cells[row][column]` with row 0 at the bottom
+    pub cells: Vec<Vec<Option<Player>>>,
+}
+
+/// Messages of a client
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Starts a game, the board of the server unless the client picks its own
+    Create {
+        version: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        win_length: Option<u32>,
+    },
+    Join {
+        version: u32,
+        game: u64,
+    },
+    /// Columns are counted from 0
+    Move {
+        column: u32,
+    },
+}
+
+/// Messages of the server
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The whole game, sent on joining and after every move
+    State {
+        version: u32,
+        game: u64,
+        /// The side of the client getting the message
+        side: Player,
+        turn: Player,
+        /// The other player has not joined yet
+        waiting: bool,
+        board: BoardState,
+        moves: Vec<u32>,
+    },
+    GameOver {
+        version: u32,
+        game: u64,
+        /// `None` for a draw
+        winner: Option<Player>,
+        /// The game ended because a player left
+        abandoned: bool,
+    },
+    /// The last message of the client was refused
+    Error { version: u32, message: String },
+}
+
+/// Why a message of a client was refused
+#[derive(Debug)]
+enum Refusal {
+    Malformed(serde_json::Error),
+    Version(u32),
+    Settings(String),
+    NoSuchGame(u64),
+    GameFull,
+    AlreadyPlaying,
+    NotPlaying,
+    Waiting,
+    NotYourTurn,
+    IllegalMove(ElemError),
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::Malformed(err) => write!(f, "malformed message: {}", err),
+            Refusal::Version(version) => write!(
+                f,
+                "schema version {} is not supported, the server speaks {}",
+                version, SCHEMA_VERSION
+            ),
+            Refusal::Settings(reason) => write!(f, "{}", reason),
+            Refusal::NoSuchGame(game) => write!(f, "there is no game {}", game),
+            Refusal::GameFull => write!(f, "the game already has two players"),
+            Refusal::AlreadyPlaying => write!(f, "you are already playing a game"),
+            Refusal::NotPlaying => write!(f, "create or join a game first"),
+            Refusal::Waiting => write!(f, "the other player has not joined yet"),
+            Refusal::NotYourTurn => write!(f, "it is not your turn"),
+            Refusal::IllegalMove(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ElementType> for Player {
+    fn from(side: ElementType) -> Player {
+        match side {
+            ElementType::Red => Player::Red,
+            ElementType::Blue => Player::Blue,
+        }
+    }
+}
+
+impl From<Player> for ElementType {
+    fn from(player: Player) -> ElementType {
+        match player {
+            Player::Red => ElementType::Red,
+            Player::Blue => ElementType::Blue,
+        }
+    }
+}
+
+impl From<&Board> for BoardState {
+    fn from(board: &Board) -> BoardState {
+        let cells = (0..board.height())
+            .map(|y| {
+                (0..board.width())
+                    .map(|x| board.get(&Pos::new(x, y)).ok().map(Player::from))
+                    .collect()
+            })
+            .collect();
+        BoardState {
+            width: board.width(),
+            height: board.height(),
+            cells,
+        }
+    }
+}
+
+pub struct WsServer {
+    listener: TcpListener,
+    settings: Settings,
+    lobby: Arc<Mutex<Lobby>>,
+}
+
+/// Games of the server by their numbers
+#[derive(Default)]
+struct Lobby {
+    last_game: u64,
+    games: HashMap<u64, HostedGame>,
+}
+
+/// One game on the server
+struct HostedGame {
+    game: GameState,
+    history: MoveHistory,
+    /// Messages for the red and blue player, by `ElementType::index`
+    players: [Option<Sender<ServerMessage>>; 2],
+}
+
+/// The game a client plays
+#[derive(Clone, Copy)]
+struct Seat {
+    game: u64,
+    side: ElementType,
+}
+
+impl WsServer {
+    /// Games created without a board of their own use these settings
+    pub fn bind(addr: impl ToSocketAddrs, settings: &Settings) -> io::Result<WsServer> {
+        Ok(WsServer {
+            listener: TcpListener::bind(addr)?,
+            settings: settings.clone(),
+            lobby: Arc::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves every client in its own thread. Runs as long as the process does.
+    pub fn run(&self) {
+        for stream in self.listener.incoming() {
+            // A client that failed to connect does not stop the others
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let settings = self.settings.clone();
+            let lobby = Arc::clone(&self.lobby);
+            thread::spawn(move || serve(stream, settings, lobby));
+        }
+    }
+}
+
+/// Talks to one client until it leaves
+fn serve(stream: TcpStream, settings: Settings, lobby: Arc<Mutex<Lobby>>) {
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+    let (outbox, inbox) = mpsc::channel();
+    let mut seat = None;
+    loop {
+        match socket.read() {
+            Ok(Frame::Text(text)) => {
+                let mut lobby = lobby.lock().unwrap();
+                if let Err(refusal) = lobby.receive(&mut seat, &outbox, &settings, &text) {
+                    let _ = outbox.send(ServerMessage::Error {
+                        version: SCHEMA_VERSION,
+                        message: refusal.to_string(),
+                    });
+                }
+            }
+            // Pings are answered by the socket itself
+            Ok(_) => {}
+            Err(WsError::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => break,
+        }
+        let sent = inbox.try_iter().try_for_each(|message| {
+            let text = serde_json::to_string(&message).expect("messages always serialize");
+            socket.send(Frame::Text(text))
+        });
+        if sent.is_err() {
+            break;
+        }
+    }
+    if let Some(seat) = seat {
+        lobby.lock().unwrap().leave(seat);
+    }
+}
+
+impl Lobby {
+    fn receive(
+        &mut self,
+        seat: &mut Option<Seat>,
+        outbox: &Sender<ServerMessage>,
+        settings: &Settings,
+        text: &str,
+    ) -> Result<(), Refusal> {
+        match serde_json::from_str(text).map_err(Refusal::Malformed)? {
+            ClientMessage::Create {
+                version,
+                width,
+                height,
+                win_length,
+            } => {
+                check_version(version)?;
+                self.leave_finished(seat)?;
+                let settings = Settings {
+                    width: width.unwrap_or(settings.width),
+                    height: height.unwrap_or(settings.height),
+                    win_length: win_length.unwrap_or(settings.win_length),
+                    language: None,
+                };
+                settings
+                    .validate()
+                    .map_err(|err| Refusal::Settings(err.to_string()))?;
+                self.last_game += 1;
+                let mut hosted = HostedGame {
+                    game: GameState::new(settings.new_board()),
+                    history: MoveHistory::new(),
+                    players: [None, None],
+                };
+                hosted.players[ElementType::Red.index() as usize] = Some(outbox.clone());
+                hosted.send_state(self.last_game);
+                self.games.insert(self.last_game, hosted);
+                *seat = Some(Seat {
+                    game: self.last_game,
+                    side: ElementType::Red,
+                });
+            }
+            ClientMessage::Join { version, game } => {
+                check_version(version)?;
+                self.leave_finished(seat)?;
+                let hosted = self.games.get_mut(&game).ok_or(Refusal::NoSuchGame(game))?;
+                let blue = &mut hosted.players[ElementType::Blue.index() as usize];
+                if blue.is_some() {
+                    return Err(Refusal::GameFull);
+                }
+                *blue = Some(outbox.clone());
+                hosted.send_state(game);
+                *seat = Some(Seat {
+                    game,
+                    side: ElementType::Blue,
+                });
+            }
+            ClientMessage::Move { column } => {
+                let seat = seat.ok_or(Refusal::NotPlaying)?;
+                let hosted = self.games.get_mut(&seat.game).ok_or(Refusal::NotPlaying)?;
+                hosted.play(seat.side, column)?;
+                hosted.send_state(seat.game);
+                if let Some(outcome) = hosted.game.outcome() {
+                    hosted.send_game_over(seat.game, outcome, false);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A client may start another game once its game is over
+    fn leave_finished(&mut self, seat: &mut Option<Seat>) -> Result<(), Refusal> {
+        let Some(current) = *seat else {
+            return Ok(());
+        };
+        let playing = self
+            .games
+            .get(&current.game)
+            .is_some_and(|hosted| !hosted.game.is_over());
+        if playing {
+            return Err(Refusal::AlreadyPlaying);
+        }
+        self.leave(current);
+        *seat = None;
+        Ok(())
+    }
+
+    /// The other player wins a game that is not over yet
+    fn leave(&mut self, seat: Seat) {
+        let Some(hosted) = self.games.get_mut(&seat.game) else {
+            return;
+        };
+        hosted.players[seat.side.index() as usize] = None;
+        if hosted.game.resign(seat.side).is_ok() {
+            hosted.send_game_over(seat.game, Outcome::Win(seat.side.other()), true);
+        }
+        if hosted.players.iter().all(Option::is_none) {
+            self.games.remove(&seat.game);
+        }
+    }
+}
+
+impl HostedGame {
+    fn play(&mut self, side: ElementType, column: u32) -> Result<(), Refusal> {
+        // After the end of the game the rules refuse any move
+        if !self.game.is_over() {
+            if self.players.iter().any(Option::is_none) {
+                return Err(Refusal::Waiting);
+            }
+            if self.game.turn() != side {
+                return Err(Refusal::NotYourTurn);
+            }
+        }
+        self.history
+            .play(&mut self.game, column)
+            .map_err(Refusal::IllegalMove)?;
+        Ok(())
+    }
+
+    fn send_state(&self, id: u64) {
+        let board = BoardState::from(self.game.board());
+        let moves: Vec<u32> = self
+            .history
+            .moves()
+            .iter()
+            .map(|played| played.column)
+            .collect();
+        let waiting = self.players.iter().any(Option::is_none);
+        for side in [ElementType::Red, ElementType::Blue] {
+            self.send(
+                side,
+                ServerMessage::State {
+                    version: SCHEMA_VERSION,
+                    game: id,
+                    side: side.into(),
+                    turn: self.game.turn().into(),
+                    waiting,
+                    board: board.clone(),
+                    moves: moves.clone(),
+                },
+            );
+        }
+    }
+
+    fn send_game_over(&self, id: u64, outcome: Outcome, abandoned: bool) {
+        let winner = match outcome {
+            Outcome::Win(side) => Some(side.into()),
+            Outcome::Draw => None,
+        };
+        for side in [ElementType::Red, ElementType::Blue] {
+            self.send(
+                side,
+                ServerMessage::GameOver {
+                    version: SCHEMA_VERSION,
+                    game: id,
+                    winner,
+                    abandoned,
+                },
+            );
+        }
+    }
+
+    /// A player who already left is skipped
+    fn send(&self, side: ElementType, message: ServerMessage) {
+        if let Some(player) = &self.players[side.index() as usize] {
+            let _ = player.send(message);
+        }
+    }
+}
+
+fn check_version(version: u32) -> Result<(), Refusal> {
+    if version == SCHEMA_VERSION {
+        Ok(())
+    } else {
+        Err(Refusal::Version(version))
+    }
+}
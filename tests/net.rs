@@ -0,0 +1,313 @@
+//! A host and a guest playing over a loopback connection.
+
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+use std::time::Duration;
+
+use connect_four::net::{Message, NetError, Peer, Session, SessionEvent, Spectator, VERSION};
+use connect_four::{ElemError, ElementType, Outcome, Settings};
+
+/// Host and guest connected on a free local port
+fn connect(settings: Settings) -> (Session, Session) {
+    let (host, guest, _) = connect_at(settings);
+    (host, guest)
+}
+
+/// Host and guest, and the address where spectators connect
+fn connect_at(settings: Settings) -> (Session, Session, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let guest = thread::spawn(move || Session::join(addr).unwrap());
+    let host = Session::host(&listener, &settings).unwrap();
+    (host, guest.join().unwrap(), addr)
+}
+
+/// Polls the host until the number of spectators changes
+fn count_spectators(host: &mut Session) -> u32 {
+    loop {
+        if let Some(SessionEvent::Spectators(count)) = host.poll().unwrap() {
+            return count;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn messages_round_trip() {
+    let messages = [
+        Message::Hello {
+            version: 1,
+            side: ElementType::Blue,
+        },
+        Message::Sync {
+            settings: Settings::default(),
+            moves: vec![3, 3, 4],
+        },
+        Message::Move { column: 0 },
+        Message::Resign,
+        Message::Rematch,
+        Message::Watch { version: 2 },
+        Message::Resigned {
+            side: ElementType::Red,
+        },
+        Message::Spectators { count: 3 },
+    ];
+    for message in messages {
+        assert_eq!(message.to_string().parse::<Message>(), Ok(message));
+    }
+    assert_eq!("move 4".parse(), Ok(Message::Move { column: 3 }));
+    assert!("move 0".parse::<Message>().is_err());
+    assert!("sync 7x6 9".parse::<Message>().is_err());
+    assert!("castle".parse::<Message>().is_err());
+}
+
+#[test]
+fn guest_gets_the_board_of_the_host() {
+    let settings = Settings {
+        width: 9,
+        height: 7,
+        win_length: 5,
+        language: Some("ru".to_string()),
+    };
+    let (host, guest) = connect(settings);
+    assert_eq!(host.side(), ElementType::Red);
+    assert_eq!(guest.side(), ElementType::Blue);
+    assert_eq!(guest.settings(), host.settings());
+    assert_eq!(guest.game().board().width(), 9);
+    assert_eq!(guest.settings().language, None);
+}
+
+#[test]
+fn bad_connections_do_not_stop_the_host() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let guest = thread::spawn(move || {
+        // A probe that closes at once
+        drop(Peer::connect(addr).unwrap());
+        // A guest of another version
+        let peer = Peer::connect(addr).unwrap();
+        peer.recv().unwrap();
+        peer.send(&Message::Hello {
+            version: VERSION + 1,
+            side: ElementType::Blue,
+        })
+        .unwrap();
+        assert!(matches!(peer.recv(), Err(NetError::Closed)));
+        // Not a guest at all
+        let peer = Peer::connect(addr).unwrap();
+        peer.recv().unwrap();
+        peer.send(&Message::Move { column: 0 }).unwrap();
+        assert!(matches!(peer.recv(), Err(NetError::Closed)));
+        Session::join(addr).unwrap()
+    });
+    let mut host = Session::host(&listener, &Settings::default()).unwrap();
+    let mut guest = guest.join().unwrap();
+    let played = host.play(3).unwrap();
+    assert_eq!(guest.wait().unwrap(), SessionEvent::Moved(played));
+}
+
+#[test]
+fn moves_are_accepted_only_on_your_own_turn() {
+    let (mut host, mut guest) = connect(Settings::default());
+    assert!(matches!(guest.play(0), Err(NetError::NotYourTurn)));
+
+    let played = host.play(3).unwrap();
+    assert!(matches!(host.play(4), Err(NetError::NotYourTurn)));
+    assert_eq!(guest.wait().unwrap(), SessionEvent::Moved(played));
+
+    let played = guest.play(3).unwrap();
+    assert_eq!(host.wait().unwrap(), SessionEvent::Moved(played));
+    assert_eq!(host.history(), guest.history());
+    assert_eq!(host.game().turn(), ElementType::Red);
+}
+
+#[test]
+fn a_move_out_of_turn_from_the_peer_ends_the_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let guest = thread::spawn(move || {
+        let peer = Peer::connect(addr).unwrap();
+        peer.recv().unwrap();
+        peer.send(&Message::Hello {
+            version: VERSION,
+            side: ElementType::Blue,
+        })
+        .unwrap();
+        peer.recv().unwrap();
+        peer.send(&Message::Move { column: 0 }).unwrap();
+        peer
+    });
+    let mut host = Session::host(&listener, &Settings::default()).unwrap();
+    let _peer = guest.join().unwrap();
+    assert!(matches!(
+        host.wait(),
+        Err(NetError::Unexpected(Message::Move { column: 0 }))
+    ));
+    assert!(host.history().is_empty());
+}
+
+#[test]
+fn a_full_column_is_rejected_before_it_is_sent() {
+    let settings = Settings {
+        width: 4,
+        height: 2,
+        win_length: 2,
+        language: None,
+    };
+    let (mut host, mut guest) = connect(settings);
+    host.play(0).unwrap();
+    guest.wait().unwrap();
+    guest.play(0).unwrap();
+    host.wait().unwrap();
+    assert!(matches!(
+        host.play(0),
+        Err(NetError::IllegalMove(ElemError::ColumnFull))
+    ));
+    // The rejected move was not sent, so it is still the host's turn
+    let played = host.play(2).unwrap();
+    assert_eq!(guest.wait().unwrap(), SessionEvent::Moved(played));
+}
+
+#[test]
+fn the_game_ends_for_both_when_one_resigns() {
+    let (mut host, mut guest) = connect(Settings::default());
+    guest.resign().unwrap();
+    assert_eq!(host.wait().unwrap(), SessionEvent::Resigned);
+    let outcome = Some(Outcome::Win(ElementType::Red));
+    assert_eq!(host.game().outcome(), outcome);
+    assert_eq!(guest.game().outcome(), outcome);
+    assert!(matches!(
+        host.play(0),
+        Err(NetError::IllegalMove(ElemError::GameAlreadyOver))
+    ));
+}
+
+#[test]
+fn rematch_starts_once_both_agree() {
+    let (mut host, mut guest) = connect(Settings::default());
+    host.play(3).unwrap();
+    guest.wait().unwrap();
+    host.resign().unwrap();
+    guest.wait().unwrap();
+
+    assert!(!host.rematch().unwrap());
+    assert_eq!(guest.wait().unwrap(), SessionEvent::RematchOffered);
+    assert!(guest.rematch().unwrap());
+    assert_eq!(host.wait().unwrap(), SessionEvent::Restarted);
+    for session in [&host, &guest] {
+        assert!(session.history().is_empty());
+        assert_eq!(session.game().outcome(), None);
+    }
+    host.play(0).unwrap();
+}
+
+#[test]
+fn closing_the_connection_is_reported() {
+    let (host, mut guest) = connect(Settings::default());
+    drop(host);
+    assert!(matches!(guest.wait(), Err(NetError::Closed)));
+}
+
+#[test]
+fn a_spectator_gets_the_game_and_every_move() {
+    let (mut host, mut guest, addr) = connect_at(Settings::default());
+    host.play(3).unwrap();
+    guest.wait().unwrap();
+
+    let spectator = thread::spawn(move || Spectator::watch(addr).unwrap());
+    assert_eq!(count_spectators(&mut host), 1);
+    let mut spectator = spectator.join().unwrap();
+    assert_eq!(spectator.history(), host.history());
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Spectators(1));
+    assert_eq!(guest.wait().unwrap(), SessionEvent::Spectators(1));
+
+    let played = guest.play(4).unwrap();
+    host.wait().unwrap();
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Moved(played));
+    let played = host.play(3).unwrap();
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Moved(played));
+
+    guest.resign().unwrap();
+    host.wait().unwrap();
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Resigned);
+    assert_eq!(
+        spectator.game().outcome(),
+        Some(Outcome::Win(ElementType::Red))
+    );
+}
+
+#[test]
+fn a_spectator_coming_after_a_resignation_sees_it() {
+    let (mut host, mut guest, addr) = connect_at(Settings::default());
+    host.play(3).unwrap();
+    guest.wait().unwrap();
+    guest.resign().unwrap();
+    assert_eq!(host.wait().unwrap(), SessionEvent::Resigned);
+
+    let spectator = thread::spawn(move || Spectator::watch(addr).unwrap());
+    assert_eq!(count_spectators(&mut host), 1);
+    let mut spectator = spectator.join().unwrap();
+    assert_eq!(spectator.history(), host.history());
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Resigned);
+    assert_eq!(
+        spectator.game().outcome(),
+        Some(Outcome::Win(ElementType::Red))
+    );
+}
+
+#[test]
+fn spectators_may_come_before_the_guest() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let spectator = thread::spawn(move || Spectator::watch(addr).unwrap());
+    let guest = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        Session::join(addr).unwrap()
+    });
+    let mut host = Session::host(&listener, &Settings::default()).unwrap();
+    let mut guest = guest.join().unwrap();
+    let mut spectator = spectator.join().unwrap();
+    assert_eq!(count_spectators(&mut host), 1);
+    assert_eq!(guest.wait().unwrap(), SessionEvent::Spectators(1));
+
+    let played = host.play(0).unwrap();
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Spectators(1));
+    assert_eq!(spectator.wait().unwrap(), SessionEvent::Moved(played));
+}
+
+#[test]
+fn spectators_cannot_change_the_game() {
+    let (mut host, mut guest, addr) = connect_at(Settings::default());
+    let spectator = thread::spawn(move || {
+        let peer = Peer::connect(addr).unwrap();
+        peer.recv().unwrap();
+        peer.send(&Message::Watch { version: VERSION }).unwrap();
+        peer.recv().unwrap();
+        peer
+    });
+    assert_eq!(count_spectators(&mut host), 1);
+    let spectator = spectator.join().unwrap();
+    assert_eq!(spectator.recv().unwrap(), Message::Spectators { count: 1 });
+
+    spectator.send(&Message::Move { column: 0 }).unwrap();
+    let answer = loop {
+        assert_eq!(host.poll().unwrap(), None);
+        if let Some(message) = spectator.try_recv().unwrap() {
+            break message;
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    assert_eq!(
+        answer,
+        Message::Sync {
+            settings: host.settings().clone(),
+            moves: Vec::new(),
+        }
+    );
+    assert!(host.history().is_empty());
+    assert_eq!(guest.wait().unwrap(), SessionEvent::Spectators(1));
+    assert_eq!(guest.poll().unwrap(), None);
+
+    drop(spectator);
+    assert_eq!(count_spectators(&mut host), 0);
+}
@@ -0,0 +1,170 @@
+//! The dedicated server and its players running in one process.
+
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+
+use connect_four::net::{Message, NetError, Peer, Session, SessionEvent, Spectator, VERSION};
+use connect_four::server::Server;
+use connect_four::{ElementType, Outcome, Settings};
+
+/// Address of a server running in the background on a free local port
+fn start_server(settings: Settings) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0", &settings).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+/// A player that the server has already greeted as red, joining in the
+/// background so that whoever connects next gets the other side
+fn connect_first(addr: SocketAddr) -> thread::JoinHandle<Session> {
+    let stream = TcpStream::connect(addr).unwrap();
+    // The side is chosen once the hello is on its way, peeking leaves it to the session
+    stream.peek(&mut [0]).unwrap();
+    let peer = Peer::new(stream).unwrap();
+    thread::spawn(move || Session::join_peer(peer).unwrap())
+}
+
+/// Two players paired by the server, red and blue
+fn join_game(addr: SocketAddr) -> (Session, Session) {
+    let red = connect_first(addr);
+    let blue = Session::join(addr).unwrap();
+    (red.join().unwrap(), blue)
+}
+
+/// A client that sends whatever it likes after the handshake
+fn join_rogue(addr: SocketAddr) -> Peer {
+    let peer = Peer::connect(addr).unwrap();
+    let Ok(Message::Hello { side, .. }) = peer.recv() else {
+        panic!("the server did not say hello");
+    };
+    peer.send(&Message::Hello {
+        version: VERSION,
+        side: side.other(),
+    })
+    .unwrap();
+    assert!(matches!(peer.recv(), Ok(Message::Sync { .. })));
+    peer
+}
+
+/// The moves of the game sent by the server
+fn synced_moves(peer: &Peer) -> Vec<u32> {
+    match peer.recv() {
+        Ok(Message::Sync { moves, .. }) => moves,
+        other => panic!("expected sync, got {:?}", other),
+    }
+}
+
+#[test]
+fn players_are_paired_in_the_order_they_connect() {
+    let addr = start_server(Settings::default());
+    let (red, blue) = join_game(addr);
+    assert_eq!(red.side(), ElementType::Red);
+    assert_eq!(blue.side(), ElementType::Blue);
+    assert_eq!(red.settings(), &Settings::default());
+}
+
+#[test]
+fn a_game_is_played_to_the_end_through_the_server() {
+    let addr = start_server(Settings::default());
+    let (mut red, mut blue) = join_game(addr);
+    for _ in 0..3 {
+        let played = red.play(0).unwrap();
+        assert_eq!(blue.wait().unwrap(), SessionEvent::Moved(played));
+        let played = blue.play(1).unwrap();
+        assert_eq!(red.wait().unwrap(), SessionEvent::Moved(played));
+    }
+    let played = red.play(0).unwrap();
+    assert_eq!(blue.wait().unwrap(), SessionEvent::Moved(played));
+    let won = Some(Outcome::Win(ElementType::Red));
+    assert_eq!(red.game().outcome(), won);
+    assert_eq!(blue.game().outcome(), won);
+
+    assert!(!blue.rematch().unwrap());
+    assert_eq!(red.wait().unwrap(), SessionEvent::RematchOffered);
+    assert!(red.rematch().unwrap());
+    assert_eq!(blue.wait().unwrap(), SessionEvent::Restarted);
+    let played = red.play(3).unwrap();
+    assert_eq!(blue.wait().unwrap(), SessionEvent::Moved(played));
+}
+
+#[test]
+fn games_run_side_by_side() {
+    let addr = start_server(Settings::default());
+    let mut games: Vec<_> = (0..3).map(|_| join_game(addr)).collect();
+    for (column, (red, blue)) in games.iter_mut().enumerate() {
+        let played = red.play(column as u32).unwrap();
+        assert_eq!(blue.wait().unwrap(), SessionEvent::Moved(played));
+    }
+    for (column, (red, blue)) in games.iter().enumerate() {
+        assert_eq!(red.history(), blue.history());
+        assert_eq!(red.history().len(), 1);
+        assert_eq!(red.history().moves()[0].column, column as u32);
+    }
+}
+
+#[test]
+fn moves_against_the_rules_are_not_passed_on() {
+    let settings = Settings {
+        width: 4,
+        height: 2,
+        win_length: 3,
+        language: None,
+    };
+    let addr = start_server(settings);
+    let red = connect_first(addr);
+    let rogue = join_rogue(addr);
+    let mut red = red.join().unwrap();
+
+    // Out of turn
+    rogue.send(&Message::Move { column: 0 }).unwrap();
+    assert!(synced_moves(&rogue).is_empty());
+
+    red.play(0).unwrap();
+    assert_eq!(rogue.recv().unwrap(), Message::Move { column: 0 });
+    rogue.send(&Message::Move { column: 0 }).unwrap();
+    red.wait().unwrap();
+    red.play(2).unwrap();
+    rogue.recv().unwrap();
+
+    // Into a full column
+    rogue.send(&Message::Move { column: 0 }).unwrap();
+    assert_eq!(synced_moves(&rogue), [0, 0, 2]);
+
+    // After the end of the game
+    rogue.send(&Message::Move { column: 3 }).unwrap();
+    red.wait().unwrap();
+    red.play(1).unwrap();
+    assert_eq!(red.game().outcome(), Some(Outcome::Win(ElementType::Red)));
+    rogue.recv().unwrap();
+    rogue.send(&Message::Move { column: 2 }).unwrap();
+    assert_eq!(synced_moves(&rogue), [0, 0, 2, 3, 1]);
+
+    // The refused moves never reached red
+    assert!(matches!(red.poll(), Ok(None)));
+    assert_eq!(red.history().len(), 5);
+}
+
+#[test]
+fn a_player_leaving_ends_the_game() {
+    let addr = start_server(Settings::default());
+    let (mut red, blue) = join_game(addr);
+    drop(blue);
+    assert!(matches!(red.wait(), Err(NetError::Closed)));
+}
+
+#[test]
+fn spectators_are_refused_without_ending_a_game() {
+    let addr = start_server(Settings::default());
+    let red = connect_first(addr);
+    assert!(Spectator::watch(addr).is_err());
+    let mut blue = Session::join(addr).unwrap();
+    let mut red = red.join().unwrap();
+    assert_eq!(red.side(), ElementType::Red);
+    assert_eq!(blue.side(), ElementType::Blue);
+
+    let played = red.play(3).unwrap();
+    assert_eq!(blue.wait().unwrap(), SessionEvent::Moved(played));
+    let played = blue.play(3).unwrap();
+    assert_eq!(red.wait().unwrap(), SessionEvent::Moved(played));
+}
@@ -0,0 +1,226 @@
+//! WebSocket clients playing on a server running in the same process.
+
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+
+use connect_four::ws::{
+    BoardState, ClientMessage, Player, ServerMessage, WsServer, SCHEMA_VERSION,
+};
+use connect_four::{GameState, Settings};
+use serde_json::json;
+use tungstenite::{stream::MaybeTlsStream, Message as Frame, WebSocket};
+
+struct Client(WebSocket<MaybeTlsStream<TcpStream>>);
+
+impl Client {
+    fn connect(addr: SocketAddr) -> Client {
+        let (socket, _) = tungstenite::connect(format!("ws://{}/", addr)).unwrap();
+        Client(socket)
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        let text = serde_json::to_string(message).unwrap();
+        self.0.send(Frame::Text(text)).unwrap();
+    }
+
+    fn recv(&mut self) -> ServerMessage {
+        loop {
+            if let Frame::Text(text) = self.0.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Plays the column and returns the moves of the game after it
+    fn play(&mut self, column: u32) -> Vec<u32> {
+        self.send(&ClientMessage::Move { column });
+        match self.recv() {
+            ServerMessage::State { moves, .. } => moves,
+            other => panic!("expected state, got {:?}", other),
+        }
+    }
+
+    fn expect_error(&mut self) {
+        assert!(matches!(self.recv(), ServerMessage::Error { .. }));
+    }
+}
+
+/// The mover plays the column and both players get the same game
+fn play_both(mover: &mut Client, other: &mut Client, column: u32) -> Vec<u32> {
+    let moves = mover.play(column);
+    assert!(matches!(other.recv(), ServerMessage::State { moves: seen, .. } if seen == moves));
+    moves
+}
+
+/// Address of a server running in the background on a free local port
+fn start_server() -> SocketAddr {
+    let server = WsServer::bind("127.0.0.1:0", &Settings::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+/// A red and a blue client in a new game on a small board
+fn start_game(addr: SocketAddr) -> (Client, Client) {
+    let mut red = Client::connect(addr);
+    red.send(&ClientMessage::Create {
+        version: SCHEMA_VERSION,
+        width: Some(4),
+        height: Some(2),
+        win_length: Some(3),
+    });
+    let ServerMessage::State { game, waiting, .. } = red.recv() else {
+        panic!("the game was not created");
+    };
+    assert!(waiting);
+
+    let mut blue = Client::connect(addr);
+    blue.send(&ClientMessage::Join {
+        version: SCHEMA_VERSION,
+        game,
+    });
+    for (client, player) in [(&mut red, Player::Red), (&mut blue, Player::Blue)] {
+        let ServerMessage::State { side, waiting, .. } = client.recv() else {
+            panic!("the game did not start");
+        };
+        assert_eq!(side, player);
+        assert!(!waiting);
+    }
+    (red, blue)
+}
+
+#[test]
+fn messages_follow_the_documented_schema() {
+    let mut game = GameState::new(Settings::default().new_board());
+    game.play(3).unwrap();
+    game.play(4).unwrap();
+    let board = BoardState::from(game.board());
+    assert_eq!(board.cells[0][3], Some(Player::Red));
+    assert_eq!(board.cells[0][4], Some(Player::Blue));
+    assert_eq!(board.cells[1][3], None);
+
+    let message = ServerMessage::GameOver {
+        version: SCHEMA_VERSION,
+        game: 12,
+        winner: None,
+        abandoned: false,
+    };
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        json!({
+            "type": "game_over",
+            "version": 1,
+            "game": 12,
+            "winner": null,
+            "abandoned": false,
+        })
+    );
+    let message: ClientMessage =
+        serde_json::from_str(r#"{ "type": "create", "version": 1, "width": 9 }"#).unwrap();
+    assert_eq!(
+        message,
+        ClientMessage::Create {
+            version: 1,
+            width: Some(9),
+            height: None,
+            win_length: None,
+        }
+    );
+}
+
+#[test]
+fn two_clients_play_a_game_to_the_end() {
+    let addr = start_server();
+    let (mut red, mut blue) = start_game(addr);
+    play_both(&mut red, &mut blue, 0);
+    play_both(&mut blue, &mut red, 0);
+    play_both(&mut red, &mut blue, 1);
+    play_both(&mut blue, &mut red, 3);
+    assert_eq!(play_both(&mut red, &mut blue, 2), [0, 0, 1, 3, 2]);
+    for client in [&mut red, &mut blue] {
+        assert!(matches!(
+            client.recv(),
+            ServerMessage::GameOver {
+                winner: Some(Player::Red),
+                abandoned: false,
+                ..
+            }
+        ));
+    }
+    blue.send(&ClientMessage::Move { column: 3 });
+    blue.expect_error();
+}
+
+#[test]
+fn moves_against_the_rules_are_refused() {
+    let addr = start_server();
+    let (mut red, mut blue) = start_game(addr);
+    blue.send(&ClientMessage::Move { column: 0 });
+    blue.expect_error();
+
+    play_both(&mut red, &mut blue, 0);
+    play_both(&mut blue, &mut red, 0);
+    red.send(&ClientMessage::Move { column: 0 });
+    red.expect_error();
+    red.send(&ClientMessage::Move { column: 9 });
+    red.expect_error();
+    assert_eq!(play_both(&mut red, &mut blue, 1), [0, 0, 1]);
+}
+
+#[test]
+fn other_versions_and_unknown_games_are_refused() {
+    let addr = start_server();
+    let mut client = Client::connect(addr);
+    client.send(&ClientMessage::Create {
+        version: SCHEMA_VERSION + 1,
+        width: None,
+        height: None,
+        win_length: None,
+    });
+    client.expect_error();
+    client.send(&ClientMessage::Join {
+        version: SCHEMA_VERSION,
+        game: 42,
+    });
+    client.expect_error();
+    client.send(&ClientMessage::Move { column: 0 });
+    client.expect_error();
+    client
+        .0
+        .send(Frame::Text("{ \"type\": \"castle\" }".into()))
+        .unwrap();
+    client.expect_error();
+}
+
+#[test]
+fn boards_too_large_are_refused() {
+    let addr = start_server();
+    let mut client = Client::connect(addr);
+    client.send(&ClientMessage::Create {
+        version: SCHEMA_VERSION,
+        width: Some(65536),
+        height: Some(65535),
+        win_length: None,
+    });
+    client.expect_error();
+
+    // The server keeps serving after the refusal
+    let (mut red, mut blue) = start_game(addr);
+    assert_eq!(play_both(&mut red, &mut blue, 0), [0]);
+}
+
+#[test]
+fn the_other_player_wins_when_one_leaves() {
+    let addr = start_server();
+    let (red, mut blue) = start_game(addr);
+    drop(red);
+    assert_eq!(
+        blue.recv(),
+        ServerMessage::GameOver {
+            version: SCHEMA_VERSION,
+            game: 1,
+            winner: Some(Player::Blue),
+            abandoned: true,
+        }
+    );
+}